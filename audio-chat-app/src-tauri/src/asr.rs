@@ -1,109 +1,672 @@
+use crate::error::{AppError, Context};
+use crate::settings::SettingsStore;
+use crate::transcript::{self, RawToken, Transcript, TranscriptSegment};
+use crate::vad;
+use crate::vocabulary::AsrContext;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use whisper_rs::{WhisperContext, WhisperContextParameters, FullParams, SamplingStrategy};
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long the Whisper model may sit unused before it is unloaded.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+pub const SAMPLE_RATE: u32 = 16000;
+// whisper.cpp skips inputs shorter than one second, so short chunks are padded with silence
+const MIN_WHISPER_SAMPLES: usize = SAMPLE_RATE as usize * 11 / 10;
+
+/// Model used until another one is selected.
+pub const DEFAULT_MODEL: &str = "base.en";
+
+/// A ggml Whisper model from the whisper.cpp releases. The id doubles as the
+/// file name: `ggml-<id>.bin`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct WhisperModel {
+    pub id: &'static str,
+    pub size: &'static str,
+    /// `.en` models are more accurate for English but can't transcribe anything else.
+    pub english_only: bool,
+    /// ggml quantization (`q5_1`, `q8_0`, ...); `None` for the full-precision model.
+    pub quantization: Option<&'static str>,
+    /// Approximate download size.
+    pub size_mb: u32,
+}
+
+impl WhisperModel {
+    pub fn file_name(&self) -> String {
+        format!("ggml-{}.bin", self.id)
+    }
+}
+
+const fn entry(id: &'static str, size: &'static str, english_only: bool, quantization: Option<&'static str>, size_mb: u32) -> WhisperModel {
+    WhisperModel { id, size, english_only, quantization, size_mb }
+}
+
+/// Every model that can be selected, smallest first.
+pub const MODELS: &[WhisperModel] = &[
+    entry("tiny.en", "tiny", true, None, 75),
+    entry("tiny.en-q5_1", "tiny", true, Some("q5_1"), 31),
+    entry("tiny.en-q8_0", "tiny", true, Some("q8_0"), 42),
+    entry("tiny", "tiny", false, None, 75),
+    entry("tiny-q5_1", "tiny", false, Some("q5_1"), 31),
+    entry("tiny-q8_0", "tiny", false, Some("q8_0"), 42),
+    entry("base.en", "base", true, None, 142),
+    entry("base.en-q5_1", "base", true, Some("q5_1"), 57),
+    entry("base.en-q8_0", "base", true, Some("q8_0"), 78),
+    entry("base", "base", false, None, 142),
+    entry("base-q5_1", "base", false, Some("q5_1"), 57),
+    entry("base-q8_0", "base", false, Some("q8_0"), 78),
+    entry("small.en", "small", true, None, 466),
+    entry("small.en-q5_1", "small", true, Some("q5_1"), 181),
+    entry("small.en-q8_0", "small", true, Some("q8_0"), 252),
+    entry("small", "small", false, None, 466),
+    entry("small-q5_1", "small", false, Some("q5_1"), 181),
+    entry("small-q8_0", "small", false, Some("q8_0"), 252),
+    // whisper.cpp only publishes q5_0 for medium
+    entry("medium.en", "medium", true, None, 1500),
+    entry("medium.en-q5_0", "medium", true, Some("q5_0"), 514),
+    entry("medium.en-q8_0", "medium", true, Some("q8_0"), 785),
+    entry("medium", "medium", false, None, 1500),
+    entry("medium-q5_0", "medium", false, Some("q5_0"), 514),
+    entry("medium-q8_0", "medium", false, Some("q8_0"), 785),
+];
+
+pub fn find_model(id: &str) -> Option<&'static WhisperModel> {
+    MODELS.iter().find(|model| model.id == id)
+}
+
+/// A registry entry as shown in the model picker.
+#[derive(Debug, Clone, Serialize)]
+pub struct WhisperModelInfo {
+    #[serde(flatten)]
+    pub model: WhisperModel,
+    /// The model file was found on disk.
+    pub installed: bool,
+    pub is_selected: bool,
+    pub is_loaded: bool,
+}
+
+/// A named set of decoding parameters, selectable at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AsrProfile {
+    /// Greedy decoding without retries. Live partial transcripts always use it.
+    Fast,
+    /// Greedy decoding that retries at higher temperatures when the result
+    /// looks off.
+    #[default]
+    Balanced,
+    /// Beam search with the same retries; noticeably slower on larger models.
+    Accurate,
+    /// The parameters in `Settings::asr_custom_decoding`.
+    Custom,
+}
+
+pub const PROFILES: &[AsrProfile] = &[AsrProfile::Fast, AsrProfile::Balanced, AsrProfile::Accurate, AsrProfile::Custom];
+
+/// Whisper decoding parameters; see whisper.cpp's `whisper_full_params`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodingOptions {
+    /// 1 decodes greedily; more keeps that many candidates (beam search).
+    pub beam_size: u32,
+    /// Temperature step for re-decoding a segment that failed the thresholds
+    /// below, up to 1.0. 0 disables the fallback.
+    pub temperature_inc: f32,
+    /// Retry when the text's entropy is below this, i.e. it is as repetitive
+    /// as text with a high compression ratio.
+    pub entropy_thold: f32,
+    /// Retry when the average token log probability is below this.
+    pub logprob_thold: f32,
+    /// A segment whose no-speech probability is above this, and which also
+    /// fails `logprob_thold`, is treated as silence.
+    pub no_speech_thold: f32,
+    /// CPU threads for inference; 0 uses every core.
+    pub threads: u32,
+    /// Keep Whisper from emitting sound annotations, music notes and the like.
+    pub suppress_non_speech: bool,
+}
+
+// whisper.cpp's WHISPER_MAX_DECODERS; whisper_full fails on larger beams
+const MAX_BEAM_SIZE: u32 = 8;
+
+// whisper.cpp's own thresholds
+const BALANCED: DecodingOptions = DecodingOptions {
+    beam_size: 1,
+    temperature_inc: 0.2,
+    entropy_thold: 2.4,
+    logprob_thold: -1.0,
+    no_speech_thold: 0.6,
+    threads: 0,
+    suppress_non_speech: true,
+};
+
+impl Default for DecodingOptions {
+    fn default() -> Self {
+        BALANCED
+    }
+}
+
+impl DecodingOptions {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(1..=MAX_BEAM_SIZE).contains(&self.beam_size) {
+            return Err(AppError::InvalidRequest(format!("Beam size must be between 1 and {}", MAX_BEAM_SIZE).into()));
+        }
+        if !(0.0..=1.0).contains(&self.temperature_inc) {
+            return Err(AppError::InvalidRequest("Temperature increment must be between 0 and 1".into()));
+        }
+        if !(0.0..=1.0).contains(&self.no_speech_thold) {
+            return Err(AppError::InvalidRequest("No-speech threshold must be between 0 and 1".into()));
+        }
+        Ok(())
+    }
+
+    fn threads(&self) -> i32 {
+        match self.threads {
+            0 => std::thread::available_parallelism().map(|n| n.get() as i32).unwrap_or(4),
+            threads => threads as i32,
+        }
+    }
+}
+
+impl AsrProfile {
+    /// Decoding parameters of the profile; `custom` is used for `Custom`.
+    pub fn options(self, custom: &DecodingOptions) -> DecodingOptions {
+        match self {
+            AsrProfile::Fast => DecodingOptions { temperature_inc: 0.0, ..BALANCED },
+            AsrProfile::Balanced => BALANCED,
+            AsrProfile::Accurate => DecodingOptions { beam_size: 5, ..BALANCED },
+            AsrProfile::Custom => *custom,
+        }
+    }
+}
+
+/// A profile as shown in the profile picker.
+#[derive(Debug, Clone, Serialize)]
+pub struct AsrProfileInfo {
+    pub profile: AsrProfile,
+    pub options: DecodingOptions,
+    pub is_selected: bool,
+}
+
+/// Every profile with its parameters, marking the selected one.
+pub fn profiles(app_handle: &tauri::AppHandle) -> Vec<AsrProfileInfo> {
+    let settings = app_handle.state::<SettingsStore>().get();
+    PROFILES
+        .iter()
+        .map(|&profile| AsrProfileInfo {
+            profile,
+            options: profile.options(&settings.asr_custom_decoding),
+            is_selected: profile == settings.asr_profile,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelLoadedEvent {
+    pub model: String,
+}
+
+// What one Whisper run produced; times are relative to the decoded samples
+struct Decoded {
+    segments: Vec<TranscriptSegment>,
+    language: &'static str,
+    model: &'static WhisperModel,
+}
+
+/// English name of a language code Whisper reports, e.g. "German" for "de".
+pub fn language_name(code: &str) -> Option<String> {
+    // whisper-rs panics on interior nul bytes
+    if code.contains('\0') {
+        return None;
+    }
+    let name = whisper_rs::get_lang_str_full(whisper_rs::get_lang_id(code)?)?;
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
+/// A transcribed recording together with its audio (mono, `SAMPLE_RATE`).
+pub struct Utterance {
+    pub transcript: Transcript,
+    pub samples: Vec<f32>,
+}
+
+struct LoadedModel {
+    ctx: Arc<WhisperContext>,
+    model: &'static WhisperModel,
+    path: PathBuf,
+    last_used: Instant,
+}
+
+/// Shared Whisper engine kept in Tauri state so the model is loaded once
+/// and reused across commands instead of being read from disk per utterance.
+pub struct AsrEngine {
+    model: Mutex<Option<LoadedModel>>,
+    // Held while a model is read so only one load runs at a time; `model`
+    // itself is only locked briefly, so unloading and status queries never
+    // wait for a load
+    loading: Mutex<()>,
+    idle_timeout: Duration,
+}
+
+impl Default for AsrEngine {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDLE_TIMEOUT)
+    }
+}
+
+impl AsrEngine {
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            model: Mutex::new(None),
+            loading: Mutex::new(()),
+            idle_timeout,
+        }
+    }
+
+    /// Returns the loaded context, loading the selected model first if
+    /// needed. A model that is no longer the selected one is replaced.
+    /// This blocks while the model is read, so call it off the async runtime.
+    pub fn context(&self, app_handle: &tauri::AppHandle) -> Result<(Arc<WhisperContext>, &'static WhisperModel), AppError> {
+        let selected = selected_model(app_handle);
+        if let Some(loaded) = self.reuse(selected)? {
+            return Ok(loaded);
+        }
+
+        let _loading = self.loading.lock()
+            .map_err(|_| AppError::Internal("ASR engine lock poisoned".into()))?;
+        // Another caller may have loaded it while we waited
+        if let Some(loaded) = self.reuse(selected)? {
+            return Ok(loaded);
+        }
+
+        let model_path = find_model_path(selected, app_handle)?;
+        println!("Loading Whisper model from: {}", model_path.display());
+
+        let ctx = WhisperContext::new_with_params(
+            model_path.to_str().unwrap(),
+            WhisperContextParameters::default()
+        ).context(AppError::Transcription, format!("Failed to load Whisper model from {}", model_path.display()))?;
+
+        let ctx = Arc::new(ctx);
+        let previous = self.model.lock()
+            .map_err(|_| AppError::Internal("ASR engine lock poisoned".into()))?
+            .replace(LoadedModel {
+                ctx: ctx.clone(),
+                model: selected,
+                path: model_path,
+                last_used: Instant::now(),
+            });
+        if let Some(previous) = previous {
+            println!("Switched Whisper model from {} to {}", previous.model.id, selected.id);
+        }
+
+        if let Err(e) = app_handle.emit("asr-model-loaded", ModelLoadedEvent { model: selected.id.to_string() }) {
+            eprintln!("Failed to emit model change: {}", e);
+        }
+
+        Ok((ctx, selected))
+    }
+
+    // The loaded context if it is `selected`, marking it as used
+    fn reuse(&self, selected: &'static WhisperModel) -> Result<Option<(Arc<WhisperContext>, &'static WhisperModel)>, AppError> {
+        let mut model = self.model.lock()
+            .map_err(|_| AppError::Internal("ASR engine lock poisoned".into()))?;
+        Ok(model.as_mut().filter(|loaded| loaded.model.id == selected.id).map(|loaded| {
+            loaded.last_used = Instant::now();
+            (loaded.ctx.clone(), loaded.model)
+        }))
+    }
+
+    /// Drops the model. Transcriptions already running keep their own
+    /// reference and finish normally; the next call reloads it.
+    pub fn unload(&self) -> bool {
+        // Take it out first so the model is freed without holding the lock
+        let taken = match self.model.lock() {
+            Ok(mut model) => model.take(),
+            Err(_) => None,
+        };
+        match taken {
+            Some(loaded) => {
+                println!("Unloaded Whisper model: {}", loaded.path.display());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn unload_if_idle(&self) -> bool {
+        let idle = match self.model.lock() {
+            Ok(model) => model
+                .as_ref()
+                .map(|loaded| loaded.last_used.elapsed() >= self.idle_timeout)
+                .unwrap_or(false),
+            Err(_) => false,
+        };
+
+        idle && self.unload()
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Id of the loaded model, if any.
+    pub fn loaded_model(&self) -> Option<&'static str> {
+        let model = self.model.lock().ok()?;
+        model.as_ref().map(|loaded| loaded.model.id)
+    }
+
+    /// The registry with what is installed, selected and loaded.
+    pub fn models(&self, app_handle: &tauri::AppHandle) -> Vec<WhisperModelInfo> {
+        let selected = selected_model(app_handle).id;
+        let loaded = self.loaded_model();
+
+        MODELS
+            .iter()
+            .map(|model| WhisperModelInfo {
+                model: *model,
+                installed: find_model_path(model, app_handle).is_ok(),
+                is_selected: model.id == selected,
+                is_loaded: Some(model.id) == loaded,
+            })
+            .collect()
+    }
+
+    /// Runs Whisper over 16kHz mono samples. Silence is trimmed with VAD first,
+    /// which also stops Whisper from hallucinating on silent tails, and long
+    /// recordings are transcribed one speech segment at a time. Segments that
+    /// look hallucinated are left out. `context` primes Whisper with the
+    /// vocabulary and fixes up the result.
+    pub fn transcribe(&self, samples: &[f32], context: &AsrContext, app_handle: &tauri::AppHandle) -> Result<Transcript, AppError> {
+        let settings = app_handle.state::<SettingsStore>().get();
+        let options = settings.asr_profile.options(&settings.asr_custom_decoding);
+
+        let chunks = vad::split_utterance(samples, SAMPLE_RATE, &vad::VadConfig::default());
+        if chunks.is_empty() {
+            return Err(AppError::NoSpeech("No speech detected in the recording".into()));
+        }
+
+        println!("VAD kept {} speech chunk(s) from {} samples", chunks.len(), samples.len());
+
+        let mut segments: Vec<TranscriptSegment> = Vec::new();
+        let mut model = None;
+        // Characters transcribed per language; the utterance is in whichever has the most
+        let mut languages: HashMap<&str, usize> = HashMap::new();
+        for chunk in chunks {
+            let offset_ms = samples_to_ms(chunk.start);
+            let decoded = self.decode_segments(&samples[chunk], context.prompt.as_deref(), &options, app_handle)?;
+            model = Some(decoded.model);
+            for mut segment in decoded.segments {
+                if let Some(reason) = transcript::hallucination(&segment, segments.last()) {
+                    println!("Dropping segment '{}' ({})", segment.text, reason);
+                    continue;
+                }
+
+                // Make times relative to the whole recording
+                segment.start_ms += offset_ms;
+                segment.end_ms += offset_ms;
+                for word in &mut segment.words {
+                    word.start_ms += offset_ms;
+                    word.end_ms += offset_ms;
+                }
+
+                *languages.entry(decoded.language).or_default() += segment.text.len();
+                segments.push(segment);
+            }
+        }
+
+        let final_transcription = segments.iter().map(|segment| segment.text.as_str()).collect::<Vec<_>>().join(" ");
+
+        if final_transcription.is_empty() {
+            return Err(AppError::NoSpeech("Transcription is empty - audio may be too short or silent".into()));
+        }
+
+        let language = languages
+            .into_iter()
+            .max_by_key(|&(_, chars)| chars)
+            .map(|(language, _)| language)
+            .unwrap_or("en");
+        println!("Detected language: {}", language);
+
+        let mut transcript = Transcript {
+            text: final_transcription,
+            language: language.to_string(),
+            model: model.map(|model| model.id.to_string()).unwrap_or_default(),
+            segments,
+        };
+        context.apply(&mut transcript);
+        println!("Final transcription: '{}'", transcript.text);
+
+        Ok(transcript)
+    }
+
+    /// Like `transcribe`, but returns an empty string for silent audio
+    /// instead of an error. Used for partial results while streaming, so it
+    /// always decodes with the fast profile.
+    pub fn decode(&self, samples: &[f32], app_handle: &tauri::AppHandle) -> Result<String, AppError> {
+        let options = AsrProfile::Fast.options(&DecodingOptions::default());
+        let mut kept: Vec<TranscriptSegment> = Vec::new();
+        for segment in self.decode_segments(samples, None, &options, app_handle)?.segments {
+            if transcript::hallucination(&segment, kept.last()).is_none() {
+                kept.push(segment);
+            }
+        }
+
+        Ok(kept.iter().map(|segment| segment.text.as_str()).collect::<Vec<_>>().join(" "))
+    }
+
+    fn decode_segments(
+        &self,
+        samples: &[f32],
+        prompt: Option<&str>,
+        options: &DecodingOptions,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<Decoded, AppError> {
+        let (ctx, model) = self.context(app_handle)?;
+
+        // Whisper reads the prompt as text that came just before the audio
+        let mut prompt_tokens = match prompt {
+            Some(prompt) => ctx.tokenize(prompt, prompt.len() + 1)
+                .context(AppError::Transcription, "Failed to tokenize initial prompt")?,
+            None => Vec::new(),
+        };
+        let max_prompt_tokens = (ctx.n_text_ctx() / 2) as usize;
+        if prompt_tokens.len() > max_prompt_tokens {
+            prompt_tokens.drain(..prompt_tokens.len() - max_prompt_tokens);
+        }
+
+        let mut padded = Vec::new();
+        let samples = if samples.len() < MIN_WHISPER_SAMPLES {
+            padded.extend_from_slice(samples);
+            padded.resize(MIN_WHISPER_SAMPLES, 0.0);
+            &padded[..]
+        } else {
+            samples
+        };
+
+        // Set up parameters for transcription
+        let strategy = match options.beam_size {
+            0 | 1 => SamplingStrategy::Greedy { best_of: 1 },
+            beam_size => SamplingStrategy::BeamSearch { beam_size: beam_size as i32, patience: -1.0 },
+        };
+        let mut params = FullParams::new(strategy);
+        params.set_n_threads(options.threads());
+        params.set_temperature_inc(options.temperature_inc);
+        params.set_entropy_thold(options.entropy_thold);
+        params.set_logprob_thold(options.logprob_thold);
+        params.set_no_speech_thold(options.no_speech_thold);
+        params.set_suppress_non_speech_tokens(options.suppress_non_speech);
+        // Multilingual models work out the language themselves
+        params.set_language(Some(if model.english_only { "en" } else { "auto" }));
+        params.set_translate(false);
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+        params.set_token_timestamps(true);
+        if !prompt_tokens.is_empty() {
+            params.set_tokens(&prompt_tokens);
+        }
+
+        // Run inference; each call gets its own state so concurrent commands don't interfere
+        let mut state = ctx.create_state().context(AppError::Transcription, "Failed to create Whisper state")?;
+        state.full(params, samples)
+            .context(AppError::Transcription, "Failed to run Whisper inference")?;
+
+        // Extract transcription
+        let num_segments = state.full_n_segments()
+            .context(AppError::Transcription, "Failed to get segment count")?;
+
+        println!("Whisper found {} segments", num_segments);
+
+        let mut segments = Vec::new();
+        for i in 0..num_segments {
+            let text = state.full_get_segment_text(i)
+                .context(AppError::Transcription, "Failed to get segment text")?;
+            // Whisper reports times in centiseconds
+            let start_ms = state.full_get_segment_t0(i)
+                .context(AppError::Transcription, "Failed to get segment start")?.max(0) as u64 * 10;
+            let end_ms = state.full_get_segment_t1(i)
+                .context(AppError::Transcription, "Failed to get segment end")?.max(0) as u64 * 10;
+
+            let num_tokens = state.full_n_tokens(i)
+                .context(AppError::Transcription, "Failed to get token count")?;
+            let mut tokens = Vec::new();
+            for j in 0..num_tokens {
+                let data = state.full_get_token_data(i, j)
+                    .context(AppError::Transcription, "Failed to get token data")?;
+                // Timestamps, language tags and the like aren't part of the text
+                if data.id >= ctx.token_eot() {
+                    continue;
+                }
+                let bytes = ctx.token_to_cstr(data.id)
+                    .context(AppError::Transcription, "Failed to get token text")?
+                    .to_bytes()
+                    .to_vec();
+                tokens.push(RawToken {
+                    bytes,
+                    probability: data.p,
+                    start_ms: data.t0.max(0) as u64 * 10,
+                    end_ms: data.t1.max(0) as u64 * 10,
+                });
+            }
+
+            let segment = TranscriptSegment::from_tokens(text, start_ms, end_ms, &tokens);
+            println!("Segment {}: '{}' (confidence {:.2})", i, segment.text, segment.confidence);
+            if !segment.text.is_empty() {
+                segments.push(segment);
+            }
+        }
+
+        let language = if model.english_only {
+            "en"
+        } else {
+            let id = state.full_lang_id_from_state()
+                .context(AppError::Transcription, "Failed to get detected language")?;
+            whisper_rs::get_lang_str(id).unwrap_or("en")
+        };
+
+        Ok(Decoded { segments, language, model })
+    }
+}
+
+fn samples_to_ms(samples: usize) -> u64 {
+    samples as u64 * 1000 / SAMPLE_RATE as u64
+}
+
+// The selected model, or the default if settings name one we don't know
+fn selected_model(app_handle: &tauri::AppHandle) -> &'static WhisperModel {
+    let id = app_handle.state::<SettingsStore>().get().whisper_model;
+    find_model(&id).unwrap_or_else(|| {
+        eprintln!("Unknown Whisper model '{}', using {}", id, DEFAULT_MODEL);
+        find_model(DEFAULT_MODEL).expect("default model is in the registry")
+    })
+}
+
+pub fn find_model_path(model: &WhisperModel, app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let file_name = model.file_name();
 
-pub async fn transcribe_audio(audio_path: String, app_handle: tauri::AppHandle) -> Result<String, String> {
     // Try multiple possible model locations
-    let possible_paths = vec![
+    let possible_paths = [
         // Development path (src-tauri/models)
         std::env::current_dir()
             .unwrap_or_default()
             .join("src-tauri")
             .join("models")
-            .join("ggml-base.en.bin"),
+            .join(&file_name),
         // Alternative development path (models in current dir)
         std::env::current_dir()
             .unwrap_or_default()
             .join("models")
-            .join("ggml-base.en.bin"),
+            .join(&file_name),
         // Production path (app data dir)
         app_handle.path().app_data_dir()
             .unwrap_or_default()
             .join("models")
-            .join("ggml-base.en.bin"),
+            .join(&file_name),
         // Resource path for bundled app
         app_handle.path().resource_dir()
             .unwrap_or_default()
             .join("models")
-            .join("ggml-base.en.bin"),
+            .join(&file_name),
     ];
     
-    let model_path = possible_paths
+    possible_paths
         .iter()
         .find(|path| path.exists())
+        .cloned()
         .ok_or_else(|| {
             let paths_str = possible_paths
                 .iter()
                 .map(|p| p.to_string_lossy().to_string())
                 .collect::<Vec<_>>()
                 .join("\n  - ");
-            format!(
-                "Whisper model not found. Searched in:\n  - {}\n\nPlease ensure ggml-base.en.bin is in one of these locations.",
-                paths_str
-            )
-        })?;
-    
-    println!("Using Whisper model at: {}", model_path.display());
-    
-    // Load audio file and convert to required format
-    let audio_data = load_audio_file(&audio_path)?;
-    
-    // Initialize Whisper context
-    let ctx = WhisperContext::new_with_params(
-        model_path.to_str().unwrap(),
-        WhisperContextParameters::default()
-    ).map_err(|e| format!("Failed to load Whisper model from {}: {}", model_path.display(), e))?;
-    
-    // Set up parameters for transcription
-    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-    params.set_language(Some("en"));
-    params.set_translate(false);
-    params.set_print_special(false);
-    params.set_print_progress(false);
-    params.set_print_realtime(false);
-    params.set_print_timestamps(false);
-    
-    // Run inference
-    let mut state = ctx.create_state().map_err(|e| format!("Failed to create Whisper state: {}", e))?;
-    state.full(params, &audio_data)
-        .map_err(|e| format!("Failed to run Whisper inference: {}", e))?;
-    
-    // Extract transcription
-    let num_segments = state.full_n_segments()
-        .map_err(|e| format!("Failed to get segment count: {}", e))?;
-    
-    println!("Whisper found {} segments", num_segments);
-    
-    let mut transcription = String::new();
-    for i in 0..num_segments {
-        let segment = state.full_get_segment_text(i)
-            .map_err(|e| format!("Failed to get segment text: {}", e))?;
-        println!("Segment {}: '{}'", i, segment);
-        transcription.push_str(&segment);
-    }
-    
-    let final_transcription = transcription.trim().to_string();
-    println!("Final transcription: '{}'", final_transcription);
-    
-    if final_transcription.is_empty() {
-        return Err("Transcription is empty - audio may be too short or silent".to_string());
-    }
-    
-    Ok(final_transcription)
+            AppError::ModelMissing(format!(
+                "Whisper model not found. Searched in:\n  - {}\n\nPlease download {} from https://huggingface.co/ggerganov/whisper.cpp into one of these locations.",
+                paths_str, file_name
+            ).into())
+        })
+}
+
+pub async fn transcribe_audio(
+    engine: Arc<AsrEngine>,
+    audio_path: String,
+    context: AsrContext,
+    app_handle: tauri::AppHandle,
+) -> Result<Transcript, AppError> {
+    tokio::task::spawn_blocking(move || {
+        // Load audio file and convert to required format
+        let audio_data = load_audio_file(&audio_path)?;
+        engine.transcribe(&audio_data, &context, &app_handle)
+    }).await
+    .context(AppError::Internal, "Task failed")?
+}
+
+/// Loads the model ahead of the first utterance so the initial turn isn't slow.
+pub async fn preload(engine: Arc<AsrEngine>, app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    tokio::task::spawn_blocking(move || engine.context(&app_handle).map(|_| ()))
+        .await
+        .context(AppError::Internal, "Task failed")?
 }
 
-fn load_audio_file(path: &str) -> Result<Vec<f32>, String> {
+fn load_audio_file(path: &str) -> Result<Vec<f32>, AppError> {
     let mut reader = hound::WavReader::open(path)
-        .map_err(|e| format!("Failed to open audio file: {}", e))?;
+        .context(AppError::UnsupportedAudio, "Failed to open audio file")?;
     
     let spec = reader.spec();
     
     // Whisper expects 16kHz mono audio
     if spec.sample_rate != 16000 {
-        return Err("Audio must be 16kHz sample rate".to_string());
+        return Err(AppError::UnsupportedAudio("Audio must be 16kHz sample rate".into()));
     }
     
     if spec.channels != 1 {
-        return Err("Audio must be mono (1 channel)".to_string());
+        return Err(AppError::UnsupportedAudio("Audio must be mono (1 channel)".into()));
     }
     
     let samples: Result<Vec<f32>, _> = match spec.sample_format {
@@ -117,5 +680,33 @@ fn load_audio_file(path: &str) -> Result<Vec<f32>, String> {
         }
     };
     
-    samples.map_err(|e| format!("Failed to read audio samples: {}", e))
-}
\ No newline at end of file
+    samples.context(AppError::UnsupportedAudio, "Failed to read audio samples")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_decoding_options() {
+        assert!(DecodingOptions::default().validate().is_ok());
+        for profile in PROFILES {
+            assert!(profile.options(&DecodingOptions::default()).validate().is_ok(), "{:?}", profile);
+        }
+
+        let beam = |beam_size| DecodingOptions { beam_size, ..Default::default() }.validate();
+        assert!(beam(1).is_ok());
+        assert!(beam(MAX_BEAM_SIZE).is_ok());
+        assert!(matches!(beam(0), Err(AppError::InvalidRequest(_))));
+        assert!(matches!(beam(MAX_BEAM_SIZE + 1), Err(AppError::InvalidRequest(_))));
+
+        let invalid = [
+            DecodingOptions { temperature_inc: -0.1, ..Default::default() },
+            DecodingOptions { temperature_inc: 1.1, ..Default::default() },
+            DecodingOptions { no_speech_thold: 1.5, ..Default::default() },
+        ];
+        for options in invalid {
+            assert!(matches!(options.validate(), Err(AppError::InvalidRequest(_))), "{:?}", options);
+        }
+    }
+}
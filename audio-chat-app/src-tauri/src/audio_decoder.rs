@@ -0,0 +1,239 @@
+use audiopus::coder::Decoder as OpusDecoder;
+use audiopus::packet::Packet;
+use audiopus::{Channels, MutSignals, SampleRate};
+use crate::error::{AppError, Context, Failure};
+use std::io::Cursor;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// Opus always decodes at 48kHz; 120ms is the longest frame it can produce
+const OPUS_SAMPLE_RATE: u32 = 48000;
+const OPUS_MAX_FRAME_SAMPLES: usize = 5760;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerFormat {
+    Wav,
+    WebM,
+    Ogg,
+    Mp3,
+    Flac,
+    Mp4,
+    Unknown,
+}
+
+impl ContainerFormat {
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            ContainerFormat::Wav => Some("wav"),
+            ContainerFormat::WebM => Some("webm"),
+            ContainerFormat::Ogg => Some("ogg"),
+            ContainerFormat::Mp3 => Some("mp3"),
+            ContainerFormat::Flac => Some("flac"),
+            ContainerFormat::Mp4 => Some("m4a"),
+            ContainerFormat::Unknown => None,
+        }
+    }
+}
+
+/// Interleaved f32 PCM as it came out of the decoder.
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Identifies the container from its magic bytes.
+pub fn sniff_format(data: &[u8]) -> ContainerFormat {
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WAVE" {
+        ContainerFormat::Wav
+    } else if data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        ContainerFormat::WebM
+    } else if data.starts_with(b"OggS") {
+        ContainerFormat::Ogg
+    } else if data.starts_with(b"fLaC") {
+        ContainerFormat::Flac
+    } else if data.len() >= 8 && &data[4..8] == b"ftyp" {
+        ContainerFormat::Mp4
+    } else if data.starts_with(b"ID3") || (data.len() >= 2 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0) {
+        ContainerFormat::Mp3
+    } else {
+        ContainerFormat::Unknown
+    }
+}
+
+/// Decodes WebM/Opus, OGG (Opus or Vorbis), MP3, FLAC, M4A/AAC and WAV in
+/// memory. Containers and most codecs are handled by symphonia; Opus packets
+/// go through libopus, which is linked in rather than shelled out to.
+pub fn decode(data: &[u8]) -> Result<DecodedAudio, AppError> {
+    let format = sniff_format(data);
+    println!("Detected audio container: {:?}", format);
+
+    let mut hint = Hint::new();
+    if let Some(extension) = format.extension() {
+        hint.with_extension(extension);
+    }
+
+    let source = MediaSourceStream::new(Box::new(Cursor::new(data.to_vec())), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+        .context(AppError::UnsupportedAudio, format!("Unsupported or corrupt audio ({:?})", format))?;
+
+    let mut reader = probed.format;
+    let track = reader
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| AppError::UnsupportedAudio("No audio track found".into()))?
+        .clone();
+
+    let channels = track.codec_params.channels
+        .map(|channels| channels.count() as u16)
+        .unwrap_or(1);
+
+    if track.codec_params.codec == CODEC_TYPE_OPUS {
+        return decode_opus(reader.as_mut(), track.id, channels);
+    }
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context(AppError::UnsupportedAudio, "Unsupported audio codec")?;
+
+    let mut samples = Vec::new();
+    let mut sample_rate = track.codec_params.sample_rate;
+    let mut channels = channels;
+
+    while let Some(packet) = next_packet(reader.as_mut())? {
+        if packet.track_id() != track.id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(buffer) => {
+                let spec = *buffer.spec();
+                sample_rate = Some(spec.rate);
+                channels = spec.channels.count() as u16;
+
+                let mut interleaved = SampleBuffer::<f32>::new(buffer.capacity() as u64, spec);
+                interleaved.copy_interleaved_ref(buffer);
+                samples.extend_from_slice(interleaved.samples());
+            }
+            // A corrupt packet shouldn't throw away the whole recording
+            Err(SymphoniaError::DecodeError(e)) => eprintln!("Skipping undecodable packet: {}", e),
+            Err(e) => return Err(AppError::UnsupportedAudio(Failure::with_source("Failed to decode audio", e))),
+        }
+    }
+
+    let sample_rate = sample_rate.ok_or_else(|| AppError::UnsupportedAudio("Audio has no sample rate".into()))?;
+    println!("Decoded {} samples at {}Hz, {} channels", samples.len(), sample_rate, channels);
+
+    Ok(DecodedAudio { samples, sample_rate, channels })
+}
+
+fn decode_opus(reader: &mut dyn FormatReader, track_id: u32, channels: u16) -> Result<DecodedAudio, AppError> {
+    let opus_channels = match channels {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        n => return Err(AppError::UnsupportedAudio(format!("Unsupported Opus channel count: {}", n).into())),
+    };
+
+    let mut decoder = OpusDecoder::new(SampleRate::Hz48000, opus_channels)
+        .context(AppError::UnsupportedAudio, "Failed to create Opus decoder")?;
+
+    let mut samples = Vec::new();
+    let mut frame = vec![0.0f32; OPUS_MAX_FRAME_SAMPLES * channels as usize];
+
+    while let Some(packet) = next_packet(reader)? {
+        if packet.track_id() != track_id || packet.data.is_empty() {
+            continue;
+        }
+
+        let input = Packet::try_from(&packet.data[..])
+            .context(AppError::UnsupportedAudio, "Invalid Opus packet")?;
+        let output = MutSignals::try_from(&mut frame[..])
+            .context(AppError::UnsupportedAudio, "Invalid Opus output buffer")?;
+
+        match decoder.decode_float(Some(input), output, false) {
+            Ok(decoded) => samples.extend_from_slice(&frame[..decoded * channels as usize]),
+            Err(e) => eprintln!("Skipping undecodable Opus packet: {}", e),
+        }
+    }
+
+    println!("Decoded {} Opus samples, {} channels", samples.len(), channels);
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: OPUS_SAMPLE_RATE,
+        channels,
+    })
+}
+
+fn next_packet(reader: &mut dyn FormatReader) -> Result<Option<symphonia::core::formats::Packet>, AppError> {
+    match reader.next_packet() {
+        Ok(packet) => Ok(Some(packet)),
+        // Symphonia signals the end of the stream with an EOF IO error
+        Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(AppError::UnsupportedAudio(Failure::with_source("Failed to read audio packet", e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        std::fs::read(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e))
+    }
+
+    fn assert_decodes(name: &str, format: ContainerFormat, sample_rate: u32, channels: u16) {
+        let data = fixture(name);
+        assert_eq!(sniff_format(&data), format, "{}", name);
+
+        let decoded = decode(&data).unwrap_or_else(|e| panic!("Failed to decode {}: {}", name, e));
+        assert_eq!(decoded.sample_rate, sample_rate, "{}", name);
+        assert_eq!(decoded.channels, channels, "{}", name);
+        assert!(!decoded.samples.is_empty(), "{} decoded to no samples", name);
+    }
+
+    #[test]
+    fn decodes_webm_opus() {
+        assert_decodes("opus.webm", ContainerFormat::WebM, 48000, 1);
+    }
+
+    #[test]
+    fn decodes_ogg_opus() {
+        assert_decodes("opus.ogg", ContainerFormat::Ogg, 48000, 1);
+    }
+
+    #[test]
+    fn decodes_ogg_vorbis() {
+        assert_decodes("vorbis.ogg", ContainerFormat::Ogg, 16000, 1);
+    }
+
+    #[test]
+    fn decodes_mp3() {
+        assert_decodes("silence.mp3", ContainerFormat::Mp3, 44100, 1);
+    }
+
+    #[test]
+    fn decodes_flac() {
+        assert_decodes("tone.flac", ContainerFormat::Flac, 16000, 1);
+    }
+
+    #[test]
+    fn decodes_m4a() {
+        assert_decodes("silence.m4a", ContainerFormat::Mp4, 16000, 1);
+    }
+
+    #[test]
+    fn rejects_unknown_data() {
+        let data = b"definitely not audio";
+        assert_eq!(sniff_format(data), ContainerFormat::Unknown);
+        assert!(matches!(decode(data), Err(AppError::UnsupportedAudio(_))));
+    }
+}
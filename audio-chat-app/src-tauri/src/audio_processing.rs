@@ -1,29 +1,19 @@
-use hound::{WavReader, WavWriter, WavSpec, SampleFormat};
-use std::io::Cursor;
+use crate::audio_decoder;
+use crate::error::{AppError, Context};
+use hound::{WavReader, SampleFormat};
+use std::io::{Cursor, Write};
 
-use std::path::PathBuf;
 use rubato::{Resampler, SincFixedIn, SincInterpolationType, SincInterpolationParameters, WindowFunction};
-use std::fs;
 
-pub fn process_audio_blob(audio_data: Vec<u8>) -> Result<PathBuf, String> {
-    // Create a persistent temporary file for the processed audio
-    let temp_dir = std::env::temp_dir();
-    let temp_path = temp_dir.join(format!("whisper_audio_{}.wav", uuid::Uuid::new_v4()));
-    
-    // First, save the raw audio data to a temporary file for debugging
-    let raw_temp_path = temp_dir.join(format!("raw_audio_{}.bin", uuid::Uuid::new_v4()));
-    
+/// Decodes an uploaded recording and returns 16kHz mono samples ready for Whisper.
+/// ffmpeg is only used as a last resort when `allow_ffmpeg` is set.
+pub fn process_audio_blob(audio_data: Vec<u8>, allow_ffmpeg: bool) -> Result<Vec<f32>, AppError> {
     println!("Processing audio blob: {} bytes", audio_data.len());
     
     if audio_data.is_empty() {
-        return Err("Audio data is empty".to_string());
+        return Err(AppError::InvalidRequest("Audio data is empty".into()));
     }
     
-    fs::write(&raw_temp_path, &audio_data)
-        .map_err(|e| format!("Failed to write raw audio data: {}", e))?;
-    
-    println!("Raw audio data saved to: {}", raw_temp_path.display());
-    
     // Try to read the audio data as WAV first
     let cursor = Cursor::new(&audio_data);
     let wav_result = WavReader::new(cursor);
@@ -49,26 +39,35 @@ pub fn process_audio_blob(audio_data: Vec<u8>) -> Result<PathBuf, String> {
                 }
             };
             
-            let samples = samples.map_err(|e| format!("Failed to read samples: {}", e))?;
+            let samples = samples.context(AppError::UnsupportedAudio, "Failed to read samples")?;
             println!("Read {} samples from WAV file", samples.len());
             (samples, sample_rate, channels)
         },
         Err(e) => {
-            // Not a WAV file, try to decode using ffmpeg or assume it's WebM/OGG
-            println!("Not a valid WAV file ({}), trying ffmpeg decode", e);
-            decode_with_ffmpeg(&raw_temp_path)?
+            // Not a WAV file, decode the container in-process
+            println!("Not a valid WAV file ({}), decoding in-process", e);
+            match audio_decoder::decode(&audio_data) {
+                Ok(decoded) => (decoded.samples, decoded.sample_rate, decoded.channels),
+                Err(e) if allow_ffmpeg => {
+                    println!("In-process decode failed ({}), falling back to ffmpeg", e);
+                    decode_with_ffmpeg(&audio_data)?
+                }
+                Err(e) => return Err(e),
+            }
         }
     };
     
-    // Convert to mono if stereo
-    let mut samples = if channels == 2 {
-        println!("Converting stereo to mono");
-        convert_stereo_to_mono(samples)
-    } else if channels == 1 {
-        println!("Audio is already mono");
-        samples
-    } else {
-        return Err(format!("Unsupported channel count: {}", channels));
+    // Convert to mono if needed
+    let mut samples = match channels {
+        0 => return Err(AppError::UnsupportedAudio("Audio has no channels".into())),
+        1 => {
+            println!("Audio is already mono");
+            samples
+        }
+        n => {
+            println!("Downmixing {} channels to mono", n);
+            downmix_to_mono(samples, n)
+        }
     };
     
     println!("After channel conversion: {} samples", samples.len());
@@ -78,15 +77,15 @@ pub fn process_audio_blob(audio_data: Vec<u8>) -> Result<PathBuf, String> {
     println!("Non-zero samples: {} out of {}", non_zero_samples, samples.len());
     
     if samples.len() < 1600 { // Less than 0.1 seconds at 16kHz
-        return Err("Audio is too short (less than 0.1 seconds)".to_string());
+        return Err(AppError::NoSpeech("Audio is too short (less than 0.1 seconds)".into()));
     }
     
     if non_zero_samples == 0 {
-        return Err("Audio appears to be silent (all samples are zero)".to_string());
+        return Err(AppError::NoSpeech("Audio appears to be silent (all samples are zero)".into()));
     }
     
     if non_zero_samples < samples.len() / 100 { // Less than 1% non-zero samples
-        return Err("Audio appears to be mostly silent".to_string());
+        return Err(AppError::NoSpeech("Audio appears to be mostly silent".into()));
     }
     
     // Resample to 16kHz if needed
@@ -96,60 +95,49 @@ pub fn process_audio_blob(audio_data: Vec<u8>) -> Result<PathBuf, String> {
         println!("After resampling: {} samples", samples.len());
     }
     
-    // Write the processed audio to temp file
-    let output_spec = WavSpec {
-        channels: 1,
-        sample_rate: 16000,
-        bits_per_sample: 16,
-        sample_format: SampleFormat::Int,
-    };
-    
-    let mut writer = WavWriter::create(&temp_path, output_spec)
-        .map_err(|e| format!("Failed to create WAV writer: {}", e))?;
-    
-    for sample in samples {
-        let sample_i16 = (sample * 32767.0).clamp(-32768.0, 32767.0) as i16;
-        writer.write_sample(sample_i16)
-            .map_err(|e| format!("Failed to write sample: {}", e))?;
-    }
-    
-    writer.finalize()
-        .map_err(|e| format!("Failed to finalize WAV file: {}", e))?;
-    
-    // Return the path to the temporary file
-    Ok(temp_path)
+    Ok(samples)
 }
 
-fn decode_with_ffmpeg(input_path: &std::path::Path) -> Result<(Vec<f32>, u32, u16), String> {
+fn decode_with_ffmpeg(audio_data: &[u8]) -> Result<(Vec<f32>, u32, u16), AppError> {
     use std::process::Command;
     
-    // Create a persistent temporary WAV file for ffmpeg output
-    let temp_dir = std::env::temp_dir();
-    let temp_wav_path = temp_dir.join(format!("ffmpeg_output_{}.wav", uuid::Uuid::new_v4()));
+    // Both temp files are deleted when they go out of scope
+    let mut input_file = tempfile::Builder::new()
+        .prefix("raw_audio_")
+        .tempfile()
+        .context(AppError::Internal, "Failed to create temp file")?;
+    input_file.write_all(audio_data)
+        .context(AppError::Internal, "Failed to write raw audio data")?;
+    
+    let output_file = tempfile::Builder::new()
+        .prefix("ffmpeg_output_")
+        .suffix(".wav")
+        .tempfile()
+        .context(AppError::Internal, "Failed to create temp file")?;
     
     // Use ffmpeg to convert the input to WAV
     let output = Command::new("ffmpeg")
-        .args(&[
-            "-i", input_path.to_str().unwrap(),
+        .arg("-i").arg(input_file.path())
+        .args([
             "-ar", "48000",  // Sample rate
             "-ac", "1",      // Mono
             "-acodec", "pcm_s16le", // PCM 16-bit little endian
             "-f", "wav",     // Output format
             "-y",            // Overwrite output file
             "-loglevel", "error", // Reduce ffmpeg output
-            temp_wav_path.to_str().unwrap()
         ])
+        .arg(output_file.path())
         .output()
-        .map_err(|e| format!("Failed to run ffmpeg: {}. Make sure ffmpeg is installed.", e))?;
+        .context(AppError::UnsupportedAudio, "Failed to run ffmpeg. Make sure ffmpeg is installed.")?;
     
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("ffmpeg failed: {}", stderr));
+        return Err(AppError::UnsupportedAudio(format!("ffmpeg failed: {}", stderr).into()));
     }
     
     // Now read the converted WAV file
-    let mut reader = WavReader::open(&temp_wav_path)
-        .map_err(|e| format!("Failed to read converted WAV: {}", e))?;
+    let mut reader = WavReader::open(output_file.path())
+        .context(AppError::UnsupportedAudio, "Failed to read converted WAV")?;
     
     let spec = reader.spec();
     let sample_rate = spec.sample_rate;
@@ -166,22 +154,34 @@ fn decode_with_ffmpeg(input_path: &std::path::Path) -> Result<(Vec<f32>, u32, u1
         }
     };
     
-    let samples = samples.map_err(|e| format!("Failed to read converted samples: {}", e))?;
+    let samples = samples.context(AppError::UnsupportedAudio, "Failed to read converted samples")?;
     Ok((samples, sample_rate, channels))
 }
 
-fn convert_stereo_to_mono(stereo_samples: Vec<f32>) -> Vec<f32> {
-    stereo_samples
-        .chunks_exact(2)
-        .map(|chunk| (chunk[0] + chunk[1]) / 2.0)
+fn downmix_to_mono(samples: Vec<f32>, channels: u16) -> Vec<f32> {
+    samples
+        .chunks_exact(channels as usize)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
         .collect()
 }
 
-fn resample_audio(samples: Vec<f32>, input_rate: u32, output_rate: u32) -> Result<Vec<f32>, String> {
+fn resample_audio(samples: Vec<f32>, input_rate: u32, output_rate: u32) -> Result<Vec<f32>, AppError> {
     if input_rate == output_rate {
         return Ok(samples);
     }
     
+    let mut resampler = mono_resampler(input_rate, output_rate, samples.len())?;
+    
+    let input_frames = vec![samples];
+    let output_frames = resampler.process(&input_frames, None)
+        .context(AppError::Internal, "Failed to resample")?;
+    
+    Ok(output_frames[0].clone())
+}
+
+// Mono sinc resampler taking `chunk_frames` input frames per call; shared by
+// whole-recording and streaming resampling so both sound the same
+fn mono_resampler(input_rate: u32, output_rate: u32, chunk_frames: usize) -> Result<SincFixedIn<f32>, AppError> {
     let params = SincInterpolationParameters {
         sinc_len: 256,
         f_cutoff: 0.95,
@@ -189,18 +189,75 @@ fn resample_audio(samples: Vec<f32>, input_rate: u32, output_rate: u32) -> Resul
         oversampling_factor: 256,
         window: WindowFunction::BlackmanHarris2,
     };
-    
-    let mut resampler = SincFixedIn::<f32>::new(
+
+    SincFixedIn::<f32>::new(
         output_rate as f64 / input_rate as f64,
         2.0,
         params,
-        samples.len(),
+        chunk_frames,
         1,
-    ).map_err(|e| format!("Failed to create resampler: {}", e))?;
-    
-    let input_frames = vec![samples];
-    let output_frames = resampler.process(&input_frames, None)
-        .map_err(|e| format!("Failed to resample: {}", e))?;
-    
-    Ok(output_frames[0].clone())
-}
\ No newline at end of file
+    ).context(AppError::Internal, "Failed to create resampler")
+}
+
+const STREAM_CHUNK_FRAMES: usize = 1024;
+
+/// Incremental mono resampler for audio that arrives in arbitrary-sized chunks.
+pub struct StreamResampler {
+    resampler: Option<SincFixedIn<f32>>,
+    pending: Vec<f32>,
+}
+
+impl StreamResampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Result<Self, AppError> {
+        if input_rate == 0 {
+            return Err(AppError::InvalidRequest("Sample rate must be greater than zero".into()));
+        }
+
+        let resampler = if input_rate == output_rate {
+            None
+        } else {
+            Some(mono_resampler(input_rate, output_rate, STREAM_CHUNK_FRAMES)?)
+        };
+
+        Ok(Self { resampler, pending: Vec::new() })
+    }
+
+    /// Feeds samples in and returns whatever resampled output is ready.
+    pub fn push(&mut self, samples: &[f32]) -> Result<Vec<f32>, AppError> {
+        let resampler = match self.resampler.as_mut() {
+            Some(resampler) => resampler,
+            None => return Ok(samples.to_vec()),
+        };
+
+        self.pending.extend_from_slice(samples);
+
+        let mut output = Vec::new();
+        while self.pending.len() >= resampler.input_frames_next() {
+            let frames = resampler.input_frames_next();
+            let chunk: Vec<f32> = self.pending.drain(..frames).collect();
+            let resampled = resampler.process(&[chunk], None)
+                .context(AppError::Internal, "Failed to resample")?;
+            output.extend_from_slice(&resampled[0]);
+        }
+
+        Ok(output)
+    }
+
+    /// Resamples any buffered remainder at the end of a stream.
+    pub fn flush(&mut self) -> Result<Vec<f32>, AppError> {
+        let resampler = match self.resampler.as_mut() {
+            Some(resampler) => resampler,
+            None => return Ok(Vec::new()),
+        };
+
+        if self.pending.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let remaining = std::mem::take(&mut self.pending);
+        let resampled = resampler.process_partial(Some(&[remaining]), None)
+            .context(AppError::Internal, "Failed to resample")?;
+
+        Ok(resampled[0].clone())
+    }
+}
@@ -0,0 +1,174 @@
+use crate::error::{AppError, Context};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+/// An audio file in the store. Files are named after the SHA-256 of their
+/// contents, so identical recordings are only kept once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredAudio {
+    pub file: String,
+    pub sha256: String,
+    pub duration_ms: u64,
+}
+
+/// Message audio (user recordings and spoken replies) under
+/// `<app data>/audio`.
+pub struct AudioStore {
+    dir: PathBuf,
+}
+
+impl AudioStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<Self, AppError> {
+        let dir = app_handle.path().app_data_dir()
+            .context(AppError::Internal, "Failed to get app data directory")?
+            .join("audio");
+        Ok(Self::new(dir))
+    }
+
+    /// Stores mono samples as a 16-bit WAV file.
+    pub fn store_samples(&self, samples: &[f32], sample_rate: u32) -> Result<StoredAudio, AppError> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        self.store_wav(&encode_wav(spec, samples)?)
+    }
+
+    /// Stores the TTS clips of one reply as a single WAV file. Clips that
+    /// don't match the first one's format are left out.
+    pub fn store_clips(&self, paths: &[String]) -> Result<Option<StoredAudio>, AppError> {
+        let mut format: Option<(u32, u16)> = None;
+        let mut samples = Vec::new();
+
+        for path in paths {
+            let (clip, sample_rate, channels) = read_wav(Path::new(path))?;
+            match format {
+                None => format = Some((sample_rate, channels)),
+                Some(format) if format == (sample_rate, channels) => {}
+                Some(_) => {
+                    eprintln!("Skipping clip {} with a different format", path);
+                    continue;
+                }
+            }
+            samples.extend(clip);
+        }
+
+        let Some((sample_rate, channels)) = format else {
+            return Ok(None);
+        };
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        self.store_wav(&encode_wav(spec, &samples)?).map(Some)
+    }
+
+    /// Stores an encoded WAV file as is, e.g. one restored from an export.
+    pub fn store_wav(&self, data: &[u8]) -> Result<StoredAudio, AppError> {
+        let sha256 = hex(&Sha256::digest(data));
+        let file = format!("{}.wav", sha256);
+        let duration_ms = wav_duration_ms(data)?;
+
+        let path = self.dir.join(&file);
+        if !path.exists() {
+            std::fs::create_dir_all(&self.dir)
+                .context(AppError::Internal, format!("Failed to create {}", self.dir.display()))?;
+            // Write to a temporary name first so a crash never leaves a truncated file behind
+            let partial = self.dir.join(format!("{}.partial", file));
+            std::fs::write(&partial, data)
+                .context(AppError::Internal, format!("Failed to write {}", partial.display()))?;
+            std::fs::rename(&partial, &path)
+                .context(AppError::Internal, format!("Failed to write {}", path.display()))?;
+        }
+
+        Ok(StoredAudio { file, sha256, duration_ms })
+    }
+
+    /// Full path of a stored file. Only bare file names are accepted.
+    pub fn path(&self, file: &str) -> Result<PathBuf, AppError> {
+        if file.is_empty() || file.contains(['/', '\\']) || file.starts_with('.') {
+            return Err(AppError::InvalidRequest(format!("Invalid audio file name '{}'", file).into()));
+        }
+        Ok(self.dir.join(file))
+    }
+
+    pub fn read(&self, file: &str) -> Result<Vec<u8>, AppError> {
+        let path = self.path(file)?;
+        std::fs::read(&path).context(AppError::InvalidRequest, format!("Failed to read {}", path.display()))
+    }
+
+    /// Deletes stored files, e.g. those `Database::delete_conversation`
+    /// reports as no longer referenced.
+    pub fn remove(&self, files: &[String]) {
+        for file in files {
+            let result = self.path(file).and_then(|path| {
+                std::fs::remove_file(&path).context(AppError::Internal, format!("Failed to remove {}", path.display()))
+            });
+            match result {
+                Ok(()) => println!("Removed audio {}", file),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+}
+
+/// Name `data` is stored under.
+pub fn file_name(data: &[u8]) -> String {
+    format!("{}.wav", hex(&Sha256::digest(data)))
+}
+
+fn encode_wav(spec: hound::WavSpec, samples: &[f32]) -> Result<Vec<u8>, AppError> {
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut cursor, spec)
+        .context(AppError::Internal, "Failed to start WAV file")?;
+    for &sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_sample(sample)
+            .context(AppError::Internal, "Failed to write WAV samples")?;
+    }
+    writer.finalize().context(AppError::Internal, "Failed to finish WAV file")?;
+    Ok(cursor.into_inner())
+}
+
+// Interleaved samples scaled to -1..1, plus sample rate and channel count
+fn read_wav(path: &Path) -> Result<(Vec<f32>, u32, u16), AppError> {
+    let mut reader = hound::WavReader::open(path)
+        .context(AppError::UnsupportedAudio, format!("Failed to open {}", path.display()))?;
+    let spec = reader.spec();
+
+    let samples: Result<Vec<f32>, _> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect(),
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>()
+                .map(|s| s.map(|sample| sample as f32 / scale))
+                .collect()
+        }
+    };
+
+    let samples = samples.context(AppError::UnsupportedAudio, format!("Failed to read {}", path.display()))?;
+    Ok((samples, spec.sample_rate, spec.channels))
+}
+
+fn wav_duration_ms(data: &[u8]) -> Result<u64, AppError> {
+    let reader = hound::WavReader::new(Cursor::new(data))
+        .context(AppError::UnsupportedAudio, "Not a WAV file")?;
+    let spec = reader.spec();
+    // `duration` counts frames, i.e. samples per channel
+    Ok(reader.duration() as u64 * 1000 / spec.sample_rate.max(1) as u64)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,163 @@
+use crate::asr;
+use crate::db::{Database, Message, MessageMetadata};
+use crate::error::AppError;
+use crate::ollama::{self, ChatMessage, StreamOutcome, TokenEvent};
+use crate::speech_pipeline::{SentenceSplitter, SpeechQueue};
+use crate::summaries;
+use serde::Deserialize;
+use tauri::{Emitter, Manager};
+use tokio::sync::watch;
+
+const SYSTEM_PROMPT: &str = "You are a friendly voice assistant. Your replies are read aloud, \
+so answer conversationally in a few sentences and avoid markdown, lists and code blocks.";
+
+/// Rough token count for budgeting. Ollama doesn't expose its tokenizer, and
+/// ~4 characters per token is close enough for English prose.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4) + 4
+}
+
+/// Builds the `/api/chat` message list for a conversation. The system prompt
+/// and the newest message are always kept; older turns are dropped once the
+/// history no longer fits in `token_budget`. The model is asked to answer in
+/// the language the user last spoke.
+pub fn build_context(history: &[Message], token_budget: usize) -> Vec<ChatMessage> {
+    let language = history
+        .iter()
+        .rev()
+        .find(|message| message.role == "user")
+        .and_then(|message| message.language.as_deref())
+        .and_then(asr::language_name);
+    let system = match language {
+        Some(language) => ChatMessage::new(
+            "system",
+            format!("{} The user is speaking {}; reply in {}.", SYSTEM_PROMPT, language, language),
+        ),
+        None => ChatMessage::new("system", SYSTEM_PROMPT),
+    };
+    let mut remaining = token_budget.saturating_sub(estimate_tokens(&system.content));
+
+    let mut kept = Vec::new();
+    // Interrupted replies only count for what the user actually heard
+    let mut messages = history.iter().rev().map(|message| (message, message.heard_text())).filter(|(_, content)| !content.is_empty());
+    for (message, content) in messages.by_ref() {
+        let cost = estimate_tokens(content);
+        if cost > remaining && !kept.is_empty() {
+            let dropped = messages.count() + 1;
+            println!("Context budget reached, dropped {} older messages", dropped);
+            break;
+        }
+
+        remaining = remaining.saturating_sub(cost);
+        kept.push(ChatMessage::new(&message.role, content));
+    }
+
+    std::iter::once(system).chain(kept.into_iter().rev()).collect()
+}
+
+/// A typed message to answer, as sent to `send_chat_message`.
+#[derive(Debug, Deserialize)]
+pub struct ChatRequest {
+    pub conversation_id: String,
+    pub content: String,
+    /// Language the message is in, if known; the reply uses it too.
+    pub language: Option<String>,
+    /// Keys the `llm-token` and `tts-segment` events and cancellation.
+    pub request_id: String,
+    /// Synthesize the reply sentence by sentence as it streams.
+    #[serde(default)]
+    pub speak: bool,
+}
+
+/// The messages `respond` stored, and the reply as generated.
+pub struct Reply {
+    pub outcome: StreamOutcome,
+    pub user_message_id: String,
+    /// None if the reply was cancelled before any text arrived.
+    pub message_id: Option<String>,
+}
+
+/// How `respond` should produce a reply.
+pub struct ReplyOptions<'a> {
+    /// Keys the `llm-token` events.
+    pub request_id: &'a str,
+    pub cancel: watch::Receiver<bool>,
+    /// Finished sentences are handed here to be spoken, if given.
+    pub speech: Option<&'a SpeechQueue>,
+    /// Tokens of history to send along; see `build_context`.
+    pub token_budget: usize,
+    /// The language the user spoke; both messages are tagged with it.
+    pub language: Option<&'a str>,
+}
+
+/// Saves the user's message, streams the reply to the conversation as
+/// `llm-token` events (handing finished sentences to the speech queue if
+/// given) and stores the reply. A cancelled reply is stored as interrupted.
+///
+/// Completed replies also kick off titling and summarizing in the background.
+pub async fn respond(
+    conversation_id: &str,
+    content: String,
+    options: ReplyOptions<'_>,
+    app_handle: &tauri::AppHandle,
+) -> Result<Reply, AppError> {
+    let ReplyOptions { request_id, cancel, speech, token_budget, language } = options;
+    let db = app_handle.state::<Database>();
+    let user_message_id = db.save_message(conversation_id, "user", &content, language)?;
+
+    let history = db.get_messages(conversation_id)?;
+    let messages = build_context(&history, token_budget);
+
+    // Speak each sentence as soon as it is complete instead of waiting for the whole reply
+    let mut splitter = SentenceSplitter::default();
+    let outcome = ollama::stream_chat(ollama::OLLAMA_URL, messages, cancel, |token| {
+        emit_token(app_handle, request_id, token);
+        if let Some(speech) = speech {
+            for sentence in splitter.push(token) {
+                speech.push(sentence);
+            }
+        }
+    })
+    .await?;
+
+    if !outcome.cancelled {
+        if let (Some(speech), Some(rest)) = (speech, splitter.finish()) {
+            speech.push(rest);
+        }
+    }
+
+    if outcome.text.is_empty() {
+        return Ok(Reply { outcome, user_message_id, message_id: None });
+    }
+
+    let message_id = db.save_message(conversation_id, "assistant", &outcome.text, language)?;
+    db.set_message_metadata(&message_id, &reply_metadata(&outcome))?;
+    if outcome.cancelled {
+        // How much was heard is filled in later if the frontend reports it
+        db.record_interruption(&message_id, None)?;
+    } else {
+        tauri::async_runtime::spawn(summaries::refresh(conversation_id.to_string(), app_handle.clone()));
+    }
+
+    Ok(Reply { outcome, user_message_id, message_id: Some(message_id) })
+}
+
+/// What is known about a generated reply before it is spoken.
+pub fn reply_metadata(outcome: &StreamOutcome) -> MessageMetadata {
+    MessageMetadata {
+        llm_model: Some(ollama::MODEL.to_string()),
+        prompt_tokens: outcome.prompt_tokens,
+        completion_tokens: outcome.completion_tokens,
+        ..Default::default()
+    }
+}
+
+pub fn emit_token(app_handle: &tauri::AppHandle, request_id: &str, token: &str) {
+    let event = TokenEvent {
+        request_id: request_id.to_string(),
+        token: token.to_string(),
+    };
+    if let Err(e) = app_handle.emit("llm-token", event) {
+        eprintln!("Failed to emit token: {}", e);
+    }
+}
@@ -1,14 +1,96 @@
-use rusqlite::{Connection, Result};
+use crate::audio_store::StoredAudio;
+use crate::error::{AppError, Context};
+use crate::transcript::TranscriptSegment;
+use crate::turn::TurnTimings;
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
 use tauri::Manager;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, Utc};
+
+// Enough for every distinct query in this module
+const STATEMENT_CACHE_CAPACITY: usize = 32;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Conversation {
     pub id: String,
     pub created_at: String,
+    pub title: Option<String>,
+    /// When the last message was added; the conversation's last activity.
+    pub updated_at: String,
+    pub archived: bool,
+    pub pinned: bool,
+    /// Generated for long conversations; see `summaries`.
+    pub summary: Option<String>,
+    /// How many messages `summary` covers.
+    pub summarized_messages: usize,
+}
+
+/// A message matching a full-text search.
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub message_id: String,
+    pub conversation_id: String,
+    pub conversation_title: Option<String>,
+    pub role: String,
+    pub timestamp: String,
+    /// Excerpt around the match, with matched terms wrapped in `<mark>`/`</mark>`.
+    /// The message text itself is not escaped.
+    pub snippet: String,
+}
+
+/// A word or phrase Whisper should expect, for every conversation or just one.
+#[derive(Debug, Clone, Serialize)]
+pub struct VocabularyTerm {
+    pub term: String,
+    /// `None` for terms that apply to every conversation.
+    pub conversation_id: Option<String>,
+}
+
+/// A fix-up applied to transcripts, e.g. "olama" → "Ollama". `from` matches
+/// whole words, ignoring case.
+#[derive(Debug, Clone, Serialize)]
+pub struct Replacement {
+    pub from: String,
+    pub to: String,
+}
+
+/// Optional restrictions for `search_messages`. Dates are RFC 3339 in any
+/// offset, or plain `YYYY-MM-DD` days; both ends are inclusive.
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchFilters {
+    pub role: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<usize>,
+}
+
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+const CONVERSATION_COLUMNS: &str = "id, created_at, title, updated_at, archived, pinned, summary, summary_message_count";
+
+fn conversation_from_row(row: &rusqlite::Row) -> rusqlite::Result<Conversation> {
+    Ok(Conversation {
+        id: row.get(0)?,
+        created_at: row.get(1)?,
+        title: row.get(2)?,
+        updated_at: row.get(3)?,
+        archived: row.get(4)?,
+        pinned: row.get(5)?,
+        summary: row.get(6)?,
+        summarized_messages: row.get(7)?,
+    })
+}
+
+/// Order of `get_conversations`. Pinned conversations always come first.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationSort {
+    #[default]
+    LastActivity,
+    Created,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,136 +100,1062 @@ pub struct Message {
     pub role: String, // "user" or "assistant"
     pub content: String,
     pub timestamp: String,
+    /// For interrupted replies, the part the user actually heard.
+    pub spoken_content: Option<String>,
+    pub interrupted: bool,
+    /// ISO 639-1 code of the language the turn was spoken in, if detected.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Recording or spoken reply in the audio store, if kept.
+    #[serde(default)]
+    pub audio_file: Option<String>,
+    #[serde(default)]
+    pub audio_sha256: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<MessageMetadata>,
+}
+
+impl Message {
+    /// What the user actually heard: the spoken part of an interrupted
+    /// reply, otherwise the whole content.
+    pub fn heard_text(&self) -> &str {
+        self.spoken_content.as_deref().unwrap_or(&self.content)
+    }
+}
+
+/// How a message was produced, for replaying and auditing turns. Which
+/// fields are known depends on the role and on how far the turn got.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageMetadata {
+    pub audio_duration_ms: Option<u64>,
+    pub asr_model: Option<String>,
+    /// Mean token probability of each Whisper segment.
+    pub segment_confidences: Option<Vec<f32>>,
+    /// Whisper's segments with word timings and token confidences, for
+    /// highlighting unsure words and replaying parts of the recording.
+    pub segments: Option<Vec<TranscriptSegment>>,
+    pub llm_model: Option<String>,
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    pub tts_engine: Option<String>,
+    pub timings: Option<TurnTimings>,
 }
 
-fn get_db_path(app_handle: tauri::AppHandle) -> Result<PathBuf, String> {
+pub fn database_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
     let app_data_dir = app_handle
         .path()
         .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+        .context(AppError::Database, "Failed to get app data directory")?;
     
     std::fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+        .context(AppError::Database, "Failed to create app data directory")?;
     
     Ok(app_data_dir.join("conversations.db"))
 }
 
-pub async fn init_database(app_handle: tauri::AppHandle) -> Result<(), String> {
-    let db_path = get_db_path(app_handle)?;
-    let conn = Connection::open(db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
+/// The app's single SQLite connection, managed by Tauri. It is opened and
+/// migrated in `setup`, so it is ready before any command runs.
+pub struct Database {
+    conn: Mutex<Connection>,
+}
+
+impl Database {
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let mut conn = Connection::open(path)
+            .context(AppError::Database, "Failed to open database")?;
+
+        // WAL lets reads proceed while a reply is being written
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+            .context(AppError::Database, "Failed to enable WAL")?;
+        conn.pragma_update(None, "foreign_keys", "ON")
+            .context(AppError::Database, "Failed to enable foreign keys")?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+
+        migrate(&mut conn)?;
+        println!("Opened database at {}", path.display());
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>, AppError> {
+        self.conn.lock()
+            .map_err(|_| AppError::Internal("Database lock poisoned".into()))
+    }
+
+    pub fn create_conversation(&self) -> Result<String, AppError> {
+        let conn = self.conn()?;
+
+        let conversation_id = Uuid::new_v4().to_string();
+        let created_at = Utc::now().to_rfc3339();
+
+        conn.prepare_cached("INSERT INTO conversations (id, created_at, updated_at) VALUES (?1, ?2, ?2)")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([&conversation_id, &created_at])
+            .context(AppError::Database, "Failed to create conversation")?;
+
+        Ok(conversation_id)
+    }
+
+    pub fn save_message(&self, conversation_id: &str, role: &str, content: &str, language: Option<&str>) -> Result<String, AppError> {
+        let conn = self.conn()?;
+
+        let message_id = Uuid::new_v4().to_string();
+        let timestamp = Utc::now().to_rfc3339();
+
+        let tx = conn.unchecked_transaction()
+            .context(AppError::Database, "Failed to start transaction")?;
+        tx.prepare_cached("INSERT INTO messages (id, conversation_id, role, content, timestamp, language) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute(rusqlite::params![message_id, conversation_id, role, content, timestamp, language])
+            .context(AppError::Database, "Failed to save message")?;
+        tx.prepare_cached("UPDATE conversations SET updated_at = ?1 WHERE id = ?2")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([timestamp.as_str(), conversation_id])
+            .context(AppError::Database, "Failed to update conversation activity")?;
+        tx.commit()
+            .context(AppError::Database, "Failed to save message")?;
+
+        Ok(message_id)
+    }
+
+    /// Marks a reply as interrupted and stores how much of it was spoken, if known.
+    pub fn record_interruption(&self, message_id: &str, spoken_content: Option<&str>) -> Result<(), AppError> {
+        let conn = self.conn()?;
+
+        conn.prepare_cached("UPDATE messages SET interrupted = 1, spoken_content = ?1 WHERE id = ?2")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute(rusqlite::params![spoken_content, message_id])
+            .context(AppError::Database, "Failed to record interruption")?;
+
+        Ok(())
+    }
+
+    pub fn set_message_audio(&self, message_id: &str, audio: &StoredAudio) -> Result<(), AppError> {
+        let conn = self.conn()?;
+
+        conn.prepare_cached("UPDATE messages SET audio_file = ?1, audio_sha256 = ?2 WHERE id = ?3")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([audio.file.as_str(), audio.sha256.as_str(), message_id])
+            .context(AppError::Database, "Failed to save message audio")?;
+
+        Ok(())
+    }
+
+    pub fn set_message_metadata(&self, message_id: &str, metadata: &MessageMetadata) -> Result<(), AppError> {
+        let json = serde_json::to_string(metadata)
+            .context(AppError::Internal, "Failed to serialize message metadata")?;
+        let conn = self.conn()?;
+
+        conn.prepare_cached("UPDATE messages SET metadata = ?1 WHERE id = ?2")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([json.as_str(), message_id])
+            .context(AppError::Database, "Failed to save message metadata")?;
+
+        Ok(())
+    }
+
+    pub fn get_conversations(&self, sort: ConversationSort, include_archived: bool) -> Result<Vec<Conversation>, AppError> {
+        let conn = self.conn()?;
+
+        let order = match sort {
+            ConversationSort::LastActivity => "updated_at",
+            ConversationSort::Created => "created_at",
+        };
+        let mut stmt = conn
+            .prepare_cached(&format!(
+                "SELECT {} FROM conversations
+                 WHERE archived = 0 OR ?1
+                 ORDER BY pinned DESC, {} DESC",
+                CONVERSATION_COLUMNS, order
+            ))
+            .context(AppError::Database, "Failed to prepare statement")?;
+
+        let conversation_iter = stmt
+            .query_map([include_archived], conversation_from_row)
+            .context(AppError::Database, "Failed to query conversations")?;
+
+        let mut conversations = Vec::new();
+        for conversation in conversation_iter {
+            conversations.push(conversation.context(AppError::Database, "Failed to parse conversation")?);
+        }
+
+        Ok(conversations)
+    }
+
+    pub fn get_conversation(&self, conversation_id: &str) -> Result<Conversation, AppError> {
+        let conn = self.conn()?;
+
+        let conversation = conn
+            .prepare_cached(&format!("SELECT {} FROM conversations WHERE id = ?1", CONVERSATION_COLUMNS))
+            .context(AppError::Database, "Failed to prepare statement")?
+            .query_row([conversation_id], conversation_from_row)
+            .optional()
+            .context(AppError::Database, "Failed to query conversation")?;
+
+        conversation.ok_or_else(|| AppError::InvalidRequest(format!("No conversation with id {}", conversation_id).into()))
+    }
+
+    /// Stores a generated title unless the user has named the conversation meanwhile.
+    pub fn set_generated_title(&self, conversation_id: &str, title: &str) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        conn.prepare_cached("UPDATE conversations SET title = ?1 WHERE id = ?2 AND title IS NULL")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([title, conversation_id])
+            .context(AppError::Database, "Failed to store conversation title")?;
+        Ok(())
+    }
+
+    pub fn set_summary(&self, conversation_id: &str, summary: &str, summarized_messages: usize) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        conn.prepare_cached("UPDATE conversations SET summary = ?1, summary_message_count = ?2 WHERE id = ?3")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute(rusqlite::params![summary, summarized_messages, conversation_id])
+            .context(AppError::Database, "Failed to store conversation summary")?;
+        Ok(())
+    }
+
+    /// Inserts an exported conversation with its messages. Ids that are
+    /// already taken are replaced, so nothing existing is overwritten.
+    /// Returns the number of messages inserted.
+    pub fn import_conversation(&self, conversation: &Conversation, messages: &[Message]) -> Result<usize, AppError> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .context(AppError::Database, "Failed to start transaction")?;
+
+        let conversation_id = unused_id(&tx, "conversations", &conversation.id)?;
+        tx.prepare_cached(
+            "INSERT INTO conversations (id, created_at, title, updated_at, archived, pinned, summary, summary_message_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )
+        .context(AppError::Database, "Failed to prepare statement")?
+        .execute(rusqlite::params![
+            conversation_id,
+            conversation.created_at,
+            conversation.title,
+            conversation.updated_at,
+            conversation.archived,
+            conversation.pinned,
+            conversation.summary,
+            conversation.summarized_messages,
+        ])
+        .context(AppError::Database, "Failed to import conversation")?;
+
+        for message in messages {
+            let message_id = unused_id(&tx, "messages", &message.id)?;
+            tx.prepare_cached(
+                "INSERT INTO messages (id, conversation_id, role, content, timestamp, spoken_content, interrupted,
+                                       audio_file, audio_sha256, metadata, language)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute(rusqlite::params![
+                message_id,
+                conversation_id,
+                message.role,
+                message.content,
+                message.timestamp,
+                message.spoken_content,
+                message.interrupted,
+                message.audio_file,
+                message.audio_sha256,
+                metadata_json(message.metadata.as_ref())?,
+                message.language,
+            ])
+            .context(AppError::Database, "Failed to import message")?;
+        }
+
+        tx.commit()
+            .context(AppError::Database, "Failed to import conversation")?;
+        Ok(messages.len())
+    }
+
+    pub fn rename_conversation(&self, conversation_id: &str, title: &str) -> Result<(), AppError> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(AppError::InvalidRequest("Conversation title can't be empty".into()));
+        }
+
+        let conn = self.conn()?;
+        let changed = conn.prepare_cached("UPDATE conversations SET title = ?1 WHERE id = ?2")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([title, conversation_id])
+            .context(AppError::Database, "Failed to rename conversation")?;
+
+        require_conversation(changed, conversation_id)
+    }
+
+    pub fn set_archived(&self, conversation_id: &str, archived: bool) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        let changed = conn.prepare_cached("UPDATE conversations SET archived = ?1 WHERE id = ?2")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute(rusqlite::params![archived, conversation_id])
+            .context(AppError::Database, "Failed to archive conversation")?;
+
+        require_conversation(changed, conversation_id)
+    }
+
+    pub fn set_pinned(&self, conversation_id: &str, pinned: bool) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        let changed = conn.prepare_cached("UPDATE conversations SET pinned = ?1 WHERE id = ?2")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute(rusqlite::params![pinned, conversation_id])
+            .context(AppError::Database, "Failed to pin conversation")?;
+
+        require_conversation(changed, conversation_id)
+    }
+
+    /// Deletes a conversation together with its messages. Returns the audio
+    /// files no other message refers to, which the caller can delete.
+    pub fn delete_conversation(&self, conversation_id: &str) -> Result<Vec<String>, AppError> {
+        let conn = self.conn()?;
+
+        let tx = conn.unchecked_transaction()
+            .context(AppError::Database, "Failed to start transaction")?;
+        let orphaned = tx
+            .prepare_cached(
+                "SELECT DISTINCT audio_file FROM messages
+                 WHERE conversation_id = ?1 AND audio_file IS NOT NULL
+                   AND audio_file NOT IN (
+                       SELECT audio_file FROM messages WHERE conversation_id != ?1 AND audio_file IS NOT NULL
+                   )",
+            )
+            .context(AppError::Database, "Failed to prepare statement")?
+            .query_map([conversation_id], |row| row.get(0))
+            .context(AppError::Database, "Failed to query message audio")?
+            .collect::<Result<Vec<String>, _>>()
+            .context(AppError::Database, "Failed to query message audio")?;
+        tx.prepare_cached("DELETE FROM messages WHERE conversation_id = ?1")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([conversation_id])
+            .context(AppError::Database, "Failed to delete messages")?;
+        tx.prepare_cached("DELETE FROM vocabulary WHERE conversation_id = ?1")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([conversation_id])
+            .context(AppError::Database, "Failed to delete conversation vocabulary")?;
+        let changed = tx.prepare_cached("DELETE FROM conversations WHERE id = ?1")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([conversation_id])
+            .context(AppError::Database, "Failed to delete conversation")?;
+        require_conversation(changed, conversation_id)?;
+
+        tx.commit()
+            .context(AppError::Database, "Failed to delete conversation")?;
+        Ok(orphaned)
+    }
+
+    /// Full-text search over message content, best matches first. Every word
+    /// of `query` must appear; the last one may be a prefix, so results keep
+    /// up while the user is typing.
+    pub fn search_messages(&self, query: &str, filters: &SearchFilters) -> Result<Vec<SearchHit>, AppError> {
+        let Some(pattern) = fts_pattern(query) else {
+            return Ok(Vec::new());
+        };
+
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT m.id, m.conversation_id, c.title, m.role, m.timestamp,
+                        snippet(messages_fts, 0, '<mark>', '</mark>', '…', 16)
+                 FROM messages_fts
+                 JOIN messages m ON m.id = messages_fts.message_id
+                 JOIN conversations c ON c.id = m.conversation_id
+                 WHERE messages_fts MATCH ?1
+                   AND (?2 IS NULL OR m.role = ?2)
+                   AND (?3 IS NULL OR m.timestamp >= ?3)
+                   AND (?4 IS NULL OR m.timestamp <= ?4)
+                 ORDER BY rank
+                 LIMIT ?5",
+            )
+            .context(AppError::Database, "Failed to prepare statement")?;
+
+        let from = filters.from.as_deref().map(|from| search_bound(from, false)).transpose()?;
+        let to = filters.to.as_deref().map(|to| search_bound(to, true)).transpose()?;
+        let limit = filters.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        let hit_iter = stmt
+            .query_map(
+                rusqlite::params![pattern, filters.role, from, to, limit],
+                |row| {
+                    Ok(SearchHit {
+                        message_id: row.get(0)?,
+                        conversation_id: row.get(1)?,
+                        conversation_title: row.get(2)?,
+                        role: row.get(3)?,
+                        timestamp: row.get(4)?,
+                        snippet: row.get(5)?,
+                    })
+                },
+            )
+            .context(AppError::Database, "Failed to search messages")?;
+
+        let mut hits = Vec::new();
+        for hit in hit_iter {
+            hits.push(hit.context(AppError::Database, "Failed to parse search hit")?);
+        }
+
+        Ok(hits)
+    }
+
+    pub fn get_messages(&self, conversation_id: &str) -> Result<Vec<Message>, AppError> {
+        let conn = self.conn()?;
+
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT id, conversation_id, role, content, timestamp, spoken_content, interrupted,
+                        audio_file, audio_sha256, metadata, language
+                 FROM messages WHERE conversation_id = ?1 ORDER BY timestamp ASC",
+            )
+            .context(AppError::Database, "Failed to prepare statement")?;
+
+        let message_iter = stmt
+            .query_map([conversation_id], |row| {
+                Ok(Message {
+                    id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    role: row.get(2)?,
+                    content: row.get(3)?,
+                    timestamp: row.get(4)?,
+                    spoken_content: row.get(5)?,
+                    interrupted: row.get(6)?,
+                    audio_file: row.get(7)?,
+                    audio_sha256: row.get(8)?,
+                    metadata: row
+                        .get::<_, Option<String>>(9)?
+                        // Metadata is diagnostic only; never fail to load a message over it
+                        .and_then(|json| serde_json::from_str(&json).ok()),
+                    language: row.get(10)?,
+                })
+            })
+            .context(AppError::Database, "Failed to query messages")?;
+
+        let mut messages = Vec::new();
+        for message in message_iter {
+            messages.push(message.context(AppError::Database, "Failed to parse message")?);
+        }
+
+        Ok(messages)
+    }
+
+    /// Terms for every conversation, plus those of `conversation_id` if given.
+    pub fn get_vocabulary(&self, conversation_id: Option<&str>) -> Result<Vec<VocabularyTerm>, AppError> {
+        let conn = self.conn()?;
+
+        let terms = conn
+            .prepare_cached(
+                "SELECT term, conversation_id FROM vocabulary
+                 WHERE conversation_id IS NULL OR conversation_id = ?1
+                 ORDER BY conversation_id IS NOT NULL, term COLLATE NOCASE",
+            )
+            .context(AppError::Database, "Failed to prepare statement")?
+            .query_map([conversation_id], |row| {
+                Ok(VocabularyTerm {
+                    term: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                })
+            })
+            .context(AppError::Database, "Failed to query vocabulary")?
+            .collect::<Result<Vec<_>, _>>()
+            .context(AppError::Database, "Failed to query vocabulary")?;
+
+        Ok(terms)
+    }
+
+    /// Adds a term; adding one that exists already does nothing.
+    pub fn add_vocabulary_term(&self, term: &str, conversation_id: Option<&str>) -> Result<(), AppError> {
+        let term = term.trim();
+        if term.is_empty() {
+            return Err(AppError::InvalidRequest("Vocabulary terms can't be empty".into()));
+        }
+        let conn = self.conn()?;
+
+        conn.prepare_cached("INSERT OR IGNORE INTO vocabulary (term, conversation_id) VALUES (?1, ?2)")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute(rusqlite::params![term, conversation_id])
+            .context(AppError::Database, "Failed to add vocabulary term")?;
+
+        Ok(())
+    }
+
+    pub fn remove_vocabulary_term(&self, term: &str, conversation_id: Option<&str>) -> Result<(), AppError> {
+        let conn = self.conn()?;
+
+        conn.prepare_cached("DELETE FROM vocabulary WHERE term = ?1 COLLATE NOCASE AND conversation_id IS ?2")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute(rusqlite::params![term.trim(), conversation_id])
+            .context(AppError::Database, "Failed to remove vocabulary term")?;
+
+        Ok(())
+    }
+
+    pub fn get_replacements(&self) -> Result<Vec<Replacement>, AppError> {
+        let conn = self.conn()?;
+
+        let replacements = conn
+            .prepare_cached("SELECT pattern, replacement FROM replacements ORDER BY pattern")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .query_map([], |row| {
+                Ok(Replacement {
+                    from: row.get(0)?,
+                    to: row.get(1)?,
+                })
+            })
+            .context(AppError::Database, "Failed to query replacements")?
+            .collect::<Result<Vec<_>, _>>()
+            .context(AppError::Database, "Failed to query replacements")?;
 
-    conn.execute(
+        Ok(replacements)
+    }
+
+    /// Adds a replacement, or changes what an existing one replaces with.
+    pub fn set_replacement(&self, from: &str, to: &str) -> Result<(), AppError> {
+        let from = from.trim();
+        if from.is_empty() {
+            return Err(AppError::InvalidRequest("The text to replace can't be empty".into()));
+        }
+        let conn = self.conn()?;
+
+        conn.prepare_cached(
+            "INSERT INTO replacements (pattern, replacement) VALUES (?1, ?2)
+             ON CONFLICT (pattern) DO UPDATE SET replacement = excluded.replacement",
+        )
+        .context(AppError::Database, "Failed to prepare statement")?
+        .execute([from, to.trim()])
+        .context(AppError::Database, "Failed to save replacement")?;
+
+        Ok(())
+    }
+
+    pub fn remove_replacement(&self, from: &str) -> Result<(), AppError> {
+        let conn = self.conn()?;
+
+        conn.prepare_cached("DELETE FROM replacements WHERE pattern = ?1")
+            .context(AppError::Database, "Failed to prepare statement")?
+            .execute([from.trim()])
+            .context(AppError::Database, "Failed to remove replacement")?;
+
+        Ok(())
+    }
+}
+
+// `id` if no row in `table` has it yet, otherwise a fresh one
+fn unused_id(conn: &Connection, table: &str, id: &str) -> Result<String, AppError> {
+    let taken: bool = conn
+        .prepare_cached(&format!("SELECT EXISTS (SELECT 1 FROM {} WHERE id = ?1)", table))
+        .context(AppError::Database, "Failed to prepare statement")?
+        .query_row([id], |row| row.get(0))
+        .context(AppError::Database, format!("Failed to look up id in {}", table))?;
+
+    Ok(if taken { Uuid::new_v4().to_string() } else { id.to_string() })
+}
+
+fn metadata_json(metadata: Option<&MessageMetadata>) -> Result<Option<String>, AppError> {
+    metadata
+        .map(serde_json::to_string)
+        .transpose()
+        .context(AppError::Internal, "Failed to serialize message metadata")
+}
+
+fn require_conversation(changed: usize, conversation_id: &str) -> Result<(), AppError> {
+    if changed == 0 {
+        return Err(AppError::InvalidRequest(format!("No conversation with id {}", conversation_id).into()));
+    }
+    Ok(())
+}
+
+// Quotes every word so user input can't be parsed as FTS5 query syntax
+fn fts_pattern(query: &str) -> Option<String> {
+    let mut pattern = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if pattern.is_empty() {
+        return None;
+    }
+    pattern.push('*');
+    Some(pattern)
+}
+
+// Normalizes a search date to the form messages are stored in (RFC 3339 in
+// UTC) so they compare as text. A plain day covers the whole day.
+fn search_bound(date: &str, end_of_day: bool) -> Result<String, AppError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(date) {
+        return Ok(time.with_timezone(&Utc).to_rfc3339());
+    }
+
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .context(AppError::InvalidRequest, format!("Invalid search date '{}'", date))?;
+    let time = if end_of_day {
+        day.and_hms_nano_opt(23, 59, 59, 999_999_999)
+    } else {
+        day.and_hms_opt(0, 0, 0)
+    };
+    let time = time.ok_or_else(|| AppError::InvalidRequest(format!("Invalid search date '{}'", date).into()))?;
+    Ok(time.and_utc().to_rfc3339())
+}
+
+type Migration = fn(&Connection) -> Result<(), AppError>;
+
+/// Schema migrations in order. `PRAGMA user_version` records how many have
+/// been applied, so each runs once per database. Never change a migration
+/// that has shipped; append a new one instead.
+const MIGRATIONS: &[Migration] = &[
+    create_tables,
+    add_interruption_columns,
+    add_conversation_management,
+    add_summaries,
+    add_message_search,
+    add_message_audio,
+    add_message_language,
+    add_vocabulary,
+];
+
+/// Brings the schema up to date, one transaction per migration.
+fn migrate(conn: &mut Connection) -> Result<(), AppError> {
+    let version: usize = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .context(AppError::Database, "Failed to read schema version")?;
+
+    if version > MIGRATIONS.len() {
+        return Err(AppError::Database(
+            format!("Database schema version {} is newer than this app supports ({})", version, MIGRATIONS.len()).into(),
+        ));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()
+            .context(AppError::Database, "Failed to start migration")?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", index + 1)
+            .context(AppError::Database, "Failed to update schema version")?;
+        tx.commit()
+            .context(AppError::Database, format!("Failed to commit migration {}", index + 1))?;
+        println!("Migrated database to schema version {}", index + 1);
+    }
+
+    Ok(())
+}
+
+// Version 1: the original schema. `IF NOT EXISTS` because databases from
+// before migrations already have these tables at user_version 0.
+fn create_tables(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS conversations (
             id TEXT PRIMARY KEY,
             created_at TEXT NOT NULL
-        )",
-        [],
-    ).map_err(|e| format!("Failed to create conversations table: {}", e))?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS messages (
+        );
+        CREATE TABLE IF NOT EXISTS messages (
             id TEXT PRIMARY KEY,
             conversation_id TEXT NOT NULL,
             role TEXT NOT NULL,
             content TEXT NOT NULL,
             timestamp TEXT NOT NULL,
             FOREIGN KEY (conversation_id) REFERENCES conversations(id)
-        )",
-        [],
-    ).map_err(|e| format!("Failed to create messages table: {}", e))?;
+        );",
+    ).context(AppError::Database, "Failed to create tables")
+}
 
-    Ok(())
+// Version 2: barge-in. Some development databases got these columns before
+// migrations existed, so only add what is missing.
+fn add_interruption_columns(conn: &Connection) -> Result<(), AppError> {
+    add_column_if_missing(conn, "messages", "spoken_content", "TEXT")?;
+    add_column_if_missing(conn, "messages", "interrupted", "INTEGER NOT NULL DEFAULT 0")
+}
+
+// Version 3: titles, pinning and archiving, and last activity for sorting
+fn add_conversation_management(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE conversations ADD COLUMN title TEXT;
+        ALTER TABLE conversations ADD COLUMN updated_at TEXT;
+        ALTER TABLE conversations ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE conversations ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+        UPDATE conversations SET updated_at = COALESCE(
+            (SELECT MAX(timestamp) FROM messages WHERE messages.conversation_id = conversations.id),
+            created_at
+        );
+        CREATE INDEX IF NOT EXISTS messages_by_conversation ON messages (conversation_id, timestamp);",
+    ).context(AppError::Database, "Failed to add conversation management columns")
 }
 
-pub async fn create_conversation(app_handle: tauri::AppHandle) -> Result<String, String> {
-    let db_path = get_db_path(app_handle)?;
-    let conn = Connection::open(db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
+// Version 4: generated summaries of long conversations
+fn add_summaries(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE conversations ADD COLUMN summary TEXT;
+        ALTER TABLE conversations ADD COLUMN summary_message_count INTEGER NOT NULL DEFAULT 0;",
+    ).context(AppError::Database, "Failed to add summary columns")
+}
 
-    let conversation_id = Uuid::new_v4().to_string();
-    let created_at = Utc::now().to_rfc3339();
+// Version 5: full-text search. The index keeps its own copy of the text and
+// is kept in sync by triggers. Rows are matched by message id, not rowid:
+// `messages` has a TEXT primary key, so its rowid is implicit and VACUUM may
+// renumber it.
+fn add_message_search(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE messages_fts USING fts5(content, message_id UNINDEXED);
+        INSERT INTO messages_fts (content, message_id) SELECT content, id FROM messages;
+        CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+            INSERT INTO messages_fts (content, message_id) VALUES (new.content, new.id);
+        END;
+        CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+            DELETE FROM messages_fts WHERE message_id = old.id;
+        END;
+        CREATE TRIGGER messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+            UPDATE messages_fts SET content = new.content WHERE message_id = old.id;
+        END;",
+    ).context(AppError::Database, "Failed to create search index")
+}
 
-    conn.execute(
-        "INSERT INTO conversations (id, created_at) VALUES (?1, ?2)",
-        [&conversation_id, &created_at],
-    ).map_err(|e| format!("Failed to create conversation: {}", e))?;
+// Version 6: stored audio and pipeline diagnostics per message
+fn add_message_audio(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE messages ADD COLUMN audio_file TEXT;
+        ALTER TABLE messages ADD COLUMN audio_sha256 TEXT;
+        ALTER TABLE messages ADD COLUMN metadata TEXT;",
+    ).context(AppError::Database, "Failed to add message audio columns")
+}
 
-    Ok(conversation_id)
+// Version 7: detected spoken language per message
+fn add_message_language(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch("ALTER TABLE messages ADD COLUMN language TEXT;")
+        .context(AppError::Database, "Failed to add message language column")
 }
 
-pub async fn save_message(
-    conversation_id: String,
-    role: String,
-    content: String,
-    app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let db_path = get_db_path(app_handle)?;
-    let conn = Connection::open(db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
+// Version 8: custom vocabulary for Whisper and transcript replacements.
+// Terms are unique per conversation, with NULL (every conversation) counted as one scope.
+fn add_vocabulary(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE vocabulary (
+            id INTEGER PRIMARY KEY,
+            term TEXT NOT NULL,
+            conversation_id TEXT REFERENCES conversations(id)
+        );
+        CREATE UNIQUE INDEX vocabulary_term ON vocabulary (ifnull(conversation_id, ''), term COLLATE NOCASE);
+        CREATE TABLE replacements (
+            pattern TEXT PRIMARY KEY COLLATE NOCASE,
+            replacement TEXT NOT NULL
+        );",
+    ).context(AppError::Database, "Failed to create vocabulary tables")
+}
 
-    let message_id = Uuid::new_v4().to_string();
-    let timestamp = Utc::now().to_rfc3339();
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<(), AppError> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", table))
+        .context(AppError::Database, "Failed to prepare statement")?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .context(AppError::Database, format!("Failed to read columns of {}", table))?
+        .collect::<Result<Vec<_>, _>>()
+        .context(AppError::Database, format!("Failed to read columns of {}", table))?;
 
-    conn.execute(
-        "INSERT INTO messages (id, conversation_id, role, content, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
-        [&message_id, &conversation_id, &role, &content, &timestamp],
-    ).map_err(|e| format!("Failed to save message: {}", e))?;
+    if !columns.iter().any(|c| c == column) {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])
+            .context(AppError::Database, format!("Failed to add {}.{}", table, column))?;
+    }
 
     Ok(())
 }
 
-pub async fn get_conversations(app_handle: tauri::AppHandle) -> Result<Vec<Conversation>, String> {
-    let db_path = get_db_path(app_handle)?;
-    let conn = Connection::open(db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut stmt = conn
-        .prepare("SELECT id, created_at FROM conversations ORDER BY created_at DESC")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-
-    let conversation_iter = stmt
-        .query_map([], |row| {
-            Ok(Conversation {
-                id: row.get(0)?,
-                created_at: row.get(1)?,
-            })
-        })
-        .map_err(|e| format!("Failed to query conversations: {}", e))?;
+    // The schema as shipped before migrations existed, at user_version 0
+    const ORIGINAL_SCHEMA: &str = "
+        CREATE TABLE conversations (
+            id TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE messages (
+            id TEXT PRIMARY KEY,
+            conversation_id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+        );";
 
-    let mut conversations = Vec::new();
-    for conversation in conversation_iter {
-        conversations.push(conversation.map_err(|e| format!("Failed to parse conversation: {}", e))?);
+    const CONVERSATION_COLUMNS_NOW: &[&str] = &[
+        "id", "created_at", "title", "updated_at", "archived", "pinned", "summary", "summary_message_count",
+    ];
+    const MESSAGE_COLUMNS_NOW: &[&str] = &[
+        "id", "conversation_id", "role", "content", "timestamp", "spoken_content", "interrupted",
+        "audio_file", "audio_sha256", "metadata", "language",
+    ];
+    const FTS_TRIGGERS: &[&str] = &["messages_fts_delete", "messages_fts_insert", "messages_fts_update"];
+
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        conn.prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap()
+            .query_map([], |row| row.get(1))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
     }
 
-    Ok(conversations)
-}
+    fn triggers(conn: &Connection) -> Vec<String> {
+        conn.prepare("SELECT name FROM sqlite_master WHERE type = 'trigger' ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
 
-pub async fn get_messages(conversation_id: String, app_handle: tauri::AppHandle) -> Result<Vec<Message>, String> {
-    let db_path = get_db_path(app_handle)?;
-    let conn = Connection::open(db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
+    fn user_version(conn: &Connection) -> usize {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+    }
 
-    let mut stmt = conn
-        .prepare("SELECT id, conversation_id, role, content, timestamp FROM messages WHERE conversation_id = ?1 ORDER BY timestamp ASC")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-
-    let message_iter = stmt
-        .query_map([&conversation_id], |row| {
-            Ok(Message {
-                id: row.get(0)?,
-                conversation_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                timestamp: row.get(4)?,
-            })
-        })
-        .map_err(|e| format!("Failed to query messages: {}", e))?;
+    // A database as an older app version left it: the first `version`
+    // migrations applied, with a conversation in it
+    fn database_at(version: usize) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in &MIGRATIONS[..version] {
+            migration(&conn).unwrap();
+        }
+        conn.pragma_update(None, "user_version", version).unwrap();
+        seed(&conn);
+        conn
+    }
+
+    // Writes what the app at that version would have: a conversation, with
+    // its last activity once that is tracked, and one message
+    fn seed(conn: &Connection) {
+        if columns(conn, "conversations").iter().any(|c| c == "updated_at") {
+            conn.execute(
+                "INSERT INTO conversations (id, created_at, updated_at)
+                 VALUES ('c1', '2025-01-01T10:00:00+00:00', '2025-01-01T10:00:01+00:00')",
+                [],
+            )
+            .unwrap();
+        } else {
+            conn.execute("INSERT INTO conversations (id, created_at) VALUES ('c1', '2025-01-01T10:00:00+00:00')", [])
+                .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO messages (id, conversation_id, role, content, timestamp)
+             VALUES ('m1', 'c1', 'user', 'hello migrated world', '2025-01-01T10:00:01+00:00')",
+            [],
+        )
+        .unwrap();
+    }
+
+    fn assert_current(mut conn: Connection) {
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        assert_eq!(columns(&conn, "conversations"), CONVERSATION_COLUMNS_NOW);
+        assert_eq!(columns(&conn, "messages"), MESSAGE_COLUMNS_NOW);
+        assert_eq!(columns(&conn, "vocabulary"), ["id", "term", "conversation_id"]);
+        assert_eq!(columns(&conn, "replacements"), ["pattern", "replacement"]);
+        assert_eq!(triggers(&conn), FTS_TRIGGERS);
+        conn.execute("INSERT INTO messages_fts (messages_fts) VALUES ('integrity-check')", []).unwrap();
+
+        // Existing rows survive and are searchable
+        let db = Database { conn: Mutex::new(conn) };
+        let conversation = db.get_conversation("c1").unwrap();
+        assert_eq!(conversation.updated_at, "2025-01-01T10:00:01+00:00");
+        let messages = db.get_messages("c1").unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(!messages[0].interrupted);
+        let hits = db.search_messages("migr", &SearchFilters::default()).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, "m1");
+    }
+
+    #[test]
+    fn migrates_original_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(ORIGINAL_SCHEMA).unwrap();
+        seed(&conn);
+
+        assert_current(conn);
+    }
+
+    #[test]
+    fn migrates_original_schema_with_interruption_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(ORIGINAL_SCHEMA).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE messages ADD COLUMN spoken_content TEXT;
+            ALTER TABLE messages ADD COLUMN interrupted INTEGER NOT NULL DEFAULT 0;",
+        )
+        .unwrap();
+        seed(&conn);
+
+        assert_current(conn);
+    }
+
+    #[test]
+    fn migrates_every_prior_version() {
+        for version in 1..MIGRATIONS.len() {
+            assert_current(database_at(version));
+        }
+    }
+
+    #[test]
+    fn migrating_current_database_changes_nothing() {
+        let mut conn = database_at(MIGRATIONS.len());
+        let before = triggers(&conn);
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        assert_eq!(triggers(&conn), before);
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
 
-    let mut messages = Vec::new();
-    for message in message_iter {
-        messages.push(message.map_err(|e| format!("Failed to parse message: {}", e))?);
+        let result = migrate(&mut conn);
+
+        assert!(matches!(result, Err(AppError::Database(_))));
+        assert_eq!(user_version(&conn), MIGRATIONS.len() + 1);
+    }
+
+    fn schema(conn: &Connection) -> Vec<String> {
+        conn.prepare("SELECT sql FROM sqlite_master WHERE sql IS NOT NULL ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn open_configures_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(&dir.path().join("conversations.db")).unwrap();
+        let conn = db.conn().unwrap();
+
+        let journal_mode: String = conn.pragma_query_value(None, "journal_mode", |row| row.get(0)).unwrap();
+        let foreign_keys: i64 = conn.pragma_query_value(None, "foreign_keys", |row| row.get(0)).unwrap();
+        assert_eq!(journal_mode, "wal");
+        assert_eq!(foreign_keys, 1);
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn reopening_migrated_database_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conversations.db");
+        let (conversation_id, before) = {
+            let db = Database::open(&path).unwrap();
+            let conversation_id = db.create_conversation().unwrap();
+            db.save_message(&conversation_id, "user", "still here", None).unwrap();
+            let schema = schema(&db.conn().unwrap());
+            (conversation_id, schema)
+        };
+
+        let db = Database::open(&path).unwrap();
+
+        assert_eq!(schema(&db.conn().unwrap()), before);
+        assert_eq!(user_version(&db.conn().unwrap()), MIGRATIONS.len());
+        assert_eq!(db.get_messages(&conversation_id).unwrap()[0].content, "still here");
+    }
+
+    #[test]
+    fn saves_gets_and_deletes_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(&dir.path().join("conversations.db")).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+        let other_id = db.create_conversation().unwrap();
+
+        let question_id = db.save_message(&conversation_id, "user", "Wie spät ist es?", Some("de")).unwrap();
+        let reply_id = db.save_message(&conversation_id, "assistant", "Es ist drei Uhr. Und sonst?", Some("de")).unwrap();
+        db.record_interruption(&reply_id, Some("Es ist drei Uhr.")).unwrap();
+        db.save_message(&other_id, "user", "Unrelated", None).unwrap();
+
+        let messages = db.get_messages(&conversation_id).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].id, question_id);
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[0].language.as_deref(), Some("de"));
+        assert_eq!(messages[0].heard_text(), "Wie spät ist es?");
+        assert_eq!(messages[1].content, "Es ist drei Uhr. Und sonst?");
+        assert!(messages[1].interrupted);
+        assert_eq!(messages[1].heard_text(), "Es ist drei Uhr.");
+        assert!(db.get_conversation(&conversation_id).unwrap().updated_at >= messages[1].timestamp);
+
+        db.delete_conversation(&conversation_id).unwrap();
+
+        assert!(db.get_messages(&conversation_id).unwrap().is_empty());
+        assert!(matches!(db.get_conversation(&conversation_id), Err(AppError::InvalidRequest(_))));
+        assert!(matches!(db.delete_conversation(&conversation_id), Err(AppError::InvalidRequest(_))));
+        assert_eq!(db.get_messages(&other_id).unwrap().len(), 1);
+        assert!(db.search_messages("drei", &SearchFilters::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn search_index_follows_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(&dir.path().join("conversations.db")).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+        let message_id = db.save_message(&conversation_id, "user", "Tell me about otters", None).unwrap();
+
+        db.conn().unwrap()
+            .execute("UPDATE messages SET content = 'Tell me about beavers' WHERE id = ?1", [&message_id])
+            .unwrap();
+
+        assert!(db.search_messages("otters", &SearchFilters::default()).unwrap().is_empty());
+        let hits = db.search_messages("beav", &SearchFilters::default()).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, message_id);
+        assert_eq!(hits[0].snippet, "Tell me about <mark>beavers</mark>");
+        db.conn().unwrap().execute("INSERT INTO messages_fts (messages_fts) VALUES ('integrity-check')", []).unwrap();
+    }
+
+    #[test]
+    fn search_date_filters_accept_any_offset_and_plain_days() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(&dir.path().join("conversations.db")).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+        let late = db.save_message(&conversation_id, "user", "late night note", None).unwrap();
+        let early = db.save_message(&conversation_id, "user", "early morning note", None).unwrap();
+        {
+            let conn = db.conn().unwrap();
+            conn.execute("UPDATE messages SET timestamp = '2025-03-01T23:30:00.250+00:00' WHERE id = ?1", [&late]).unwrap();
+            conn.execute("UPDATE messages SET timestamp = '2025-03-02T08:00:00+00:00' WHERE id = ?1", [&early]).unwrap();
+        }
+
+        let search = |from: Option<&str>, to: Option<&str>| -> Vec<String> {
+            let filters = SearchFilters { from: from.map(str::to_string), to: to.map(str::to_string), ..Default::default() };
+            let mut ids: Vec<_> = db.search_messages("note", &filters).unwrap().into_iter().map(|hit| hit.message_id).collect();
+            ids.sort_by_key(|id| id != &late);
+            ids
+        };
+
+        assert_eq!(search(Some("2025-03-02"), None), [early.as_str()]);
+        assert_eq!(search(None, Some("2025-03-01")), [late.as_str()]);
+        assert_eq!(search(Some("2025-03-01T23:30:00Z"), Some("2025-03-02T08:00:00Z")), [late.as_str(), early.as_str()]);
+        assert_eq!(search(Some("2025-03-02T00:31:00+01:00"), None), [early.as_str()]);
+        assert_eq!(search(None, Some("2025-03-02T09:00:00+01:00")), [late.as_str(), early.as_str()]);
+        assert!(matches!(
+            db.search_messages("note", &SearchFilters { from: Some("yesterday".to_string()), ..Default::default() }),
+            Err(AppError::InvalidRequest(_))
+        ));
     }
 
-    Ok(messages)
-}
\ No newline at end of file
+    #[test]
+    fn search_survives_vacuum() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(&dir.path().join("conversations.db")).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+        let other_id = db.create_conversation().unwrap();
+        for i in 0..5 {
+            db.save_message(&other_id, "user", &format!("filler {}", i), None).unwrap();
+        }
+        let message_id = db.save_message(&conversation_id, "user", "Where do otters sleep?", None).unwrap();
+
+        // Leaves a gap in the rowids for VACUUM to close
+        db.delete_conversation(&other_id).unwrap();
+        db.conn().unwrap().execute_batch("VACUUM").unwrap();
+
+        let hits = db.search_messages("otters", &SearchFilters::default()).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, message_id);
+        assert_eq!(hits[0].conversation_id, conversation_id);
+    }
+}
@@ -0,0 +1,182 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::error::Error as StdError;
+use std::fmt;
+
+type BoxError = Box<dyn StdError + Send + Sync + 'static>;
+
+/// Errors returned by every command. The frontend receives them as
+/// `{ code, message, detail, retryable, causes }` and picks its recovery UI
+/// from `code`.
+#[derive(Debug)]
+pub enum AppError {
+    /// Ollama isn't running or can't be reached.
+    OllamaUnavailable(Failure),
+    /// Ollama answered, but the request failed (e.g. the model isn't pulled).
+    Ollama(Failure),
+    /// The Whisper model file couldn't be found.
+    ModelMissing(Failure),
+    /// Whisper failed to load or run.
+    Transcription(Failure),
+    /// The recording contained no recognisable speech.
+    NoSpeech(Failure),
+    /// The audio couldn't be decoded.
+    UnsupportedAudio(Failure),
+    /// There is no microphone, or it couldn't be opened.
+    Microphone(Failure),
+    /// Speech synthesis failed.
+    Synthesis(Failure),
+    /// Reading or writing conversations failed.
+    Database(Failure),
+    /// The request refers to something that doesn't exist or can't be done right now.
+    InvalidRequest(Failure),
+    /// Anything else: poisoned locks, failed background tasks, settings I/O.
+    Internal(Failure),
+}
+
+/// What was being attempted, plus the underlying error if there is one.
+#[derive(Debug)]
+pub struct Failure {
+    message: String,
+    source: Option<BoxError>,
+}
+
+impl Failure {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into(), source: None }
+    }
+
+    pub fn with_source(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self { message: message.into(), source: Some(source.into()) }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<&str> for Failure {
+    fn from(message: &str) -> Self {
+        Self::new(message)
+    }
+}
+
+impl AppError {
+    fn failure(&self) -> &Failure {
+        match self {
+            AppError::OllamaUnavailable(f)
+            | AppError::Ollama(f)
+            | AppError::ModelMissing(f)
+            | AppError::Transcription(f)
+            | AppError::NoSpeech(f)
+            | AppError::UnsupportedAudio(f)
+            | AppError::Microphone(f)
+            | AppError::Synthesis(f)
+            | AppError::Database(f)
+            | AppError::InvalidRequest(f)
+            | AppError::Internal(f) => f,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::OllamaUnavailable(_) => "ollama_unavailable",
+            AppError::Ollama(_) => "ollama_failed",
+            AppError::ModelMissing(_) => "model_missing",
+            AppError::Transcription(_) => "transcription_failed",
+            AppError::NoSpeech(_) => "no_speech",
+            AppError::UnsupportedAudio(_) => "unsupported_audio",
+            AppError::Microphone(_) => "microphone_unavailable",
+            AppError::Synthesis(_) => "synthesis_failed",
+            AppError::Database(_) => "database_failed",
+            AppError::InvalidRequest(_) => "invalid_request",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    /// Whether trying the same thing again can reasonably succeed.
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            AppError::OllamaUnavailable(_)
+                | AppError::Ollama(_)
+                | AppError::Transcription(_)
+                | AppError::NoSpeech(_)
+                | AppError::Microphone(_)
+                | AppError::Synthesis(_)
+                | AppError::Database(_)
+        )
+    }
+
+    /// Short explanation suitable for showing to the user as-is.
+    pub fn user_message(&self) -> &'static str {
+        match self {
+            AppError::OllamaUnavailable(_) => "Can't reach Ollama. Make sure it is running with 'ollama run gemma3n:latest'.",
+            AppError::Ollama(_) => "The language model couldn't answer.",
+            AppError::ModelMissing(_) => "The Whisper speech model is missing.",
+            AppError::Transcription(_) => "Speech recognition failed.",
+            AppError::NoSpeech(_) => "No speech was detected. Try again a little closer to the microphone.",
+            AppError::UnsupportedAudio(_) => "This audio format isn't supported.",
+            AppError::Microphone(_) => "The microphone couldn't be used.",
+            AppError::Synthesis(_) => "The spoken reply couldn't be generated.",
+            AppError::Database(_) => "The conversation history couldn't be accessed.",
+            AppError::InvalidRequest(_) => "That isn't possible right now.",
+            AppError::Internal(_) => "Something went wrong.",
+        }
+    }
+
+    /// Messages of the underlying errors, outermost first.
+    pub fn causes(&self) -> Vec<String> {
+        let mut causes = Vec::new();
+        let mut source = StdError::source(self);
+        while let Some(error) = source {
+            causes.push(error.to_string());
+            source = error.source();
+        }
+        causes
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failure = self.failure();
+        match &failure.source {
+            Some(source) => write!(f, "{}: {}", failure.message, source),
+            None => write!(f, "{}", failure.message),
+        }
+    }
+}
+
+impl StdError for AppError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.failure().source.as_deref().map(|e| e as &(dyn StdError + 'static))
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 5)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", self.user_message())?;
+        state.serialize_field("detail", &self.to_string())?;
+        state.serialize_field("retryable", &self.retryable())?;
+        state.serialize_field("causes", &self.causes())?;
+        state.end()
+    }
+}
+
+/// Attaches a category and a description of what was being attempted to a
+/// lower-level error, e.g. `.context(AppError::Database, "Failed to open database")`.
+pub trait Context<T> {
+    fn context(self, kind: fn(Failure) -> AppError, message: impl Into<String>) -> Result<T, AppError>;
+}
+
+impl<T, E> Context<T> for Result<T, E>
+where
+    E: Into<BoxError>,
+{
+    fn context(self, kind: fn(Failure) -> AppError, message: impl Into<String>) -> Result<T, AppError> {
+        self.map_err(|e| kind(Failure::with_source(message, e)))
+    }
+}
@@ -0,0 +1,423 @@
+use crate::audio_store::{self, AudioStore};
+use crate::db::{Conversation, Database, Message};
+use crate::error::{AppError, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+// Identifies our JSON files so importing something else fails clearly
+const EXPORT_FORMAT: &str = "audio-chat-export";
+const EXPORT_VERSION: u32 = 1;
+const BUNDLE_JSON: &str = "conversations.json";
+const BUNDLE_AUDIO_DIR: &str = "audio/";
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Lossless; can be imported again.
+    Json,
+    /// Readable transcripts; export only.
+    Markdown,
+    /// Zip with the JSON, one Markdown transcript per conversation and the
+    /// messages' stored audio.
+    Bundle,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportFile {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub conversations: Vec<ExportedConversation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedConversation {
+    #[serde(flatten)]
+    pub conversation: Conversation,
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub conversations: usize,
+    pub messages: usize,
+    pub audio_files: usize,
+}
+
+/// Writes one conversation, or all of them, to a new file in `dir` and
+/// returns its path.
+pub fn export(
+    db: &Database,
+    audio: &AudioStore,
+    conversation_id: Option<&str>,
+    format: ExportFormat,
+    dir: &Path,
+) -> Result<PathBuf, AppError> {
+    let file = collect(db, conversation_id)?;
+
+    let name = match conversation_id {
+        Some(id) => id.split('-').next().unwrap_or(id).to_string(),
+        None => "all".to_string(),
+    };
+    let extension = match format {
+        ExportFormat::Json => "json",
+        ExportFormat::Markdown => "md",
+        ExportFormat::Bundle => "zip",
+    };
+    let path = dir.join(format!("audio-chat-{}-{}.{}", name, Utc::now().format("%Y%m%d-%H%M%S"), extension));
+
+    let data = match format {
+        ExportFormat::Json => to_json(&file)?,
+        ExportFormat::Markdown => file.conversations.iter().map(to_markdown).collect::<Vec<_>>().join("\n---\n\n").into_bytes(),
+        ExportFormat::Bundle => to_bundle(&file, audio)?,
+    };
+
+    std::fs::create_dir_all(dir)
+        .context(AppError::Internal, format!("Failed to create {}", dir.display()))?;
+    std::fs::write(&path, data)
+        .context(AppError::Internal, format!("Failed to write {}", path.display()))?;
+
+    println!("Exported {} conversations to {}", file.conversations.len(), path.display());
+    Ok(path)
+}
+
+/// Imports a JSON export or a bundle. Conversations and messages whose ids
+/// are already taken get new ids, so importing never overwrites anything.
+/// Audio references are kept only if the audio is in the bundle or already
+/// stored.
+pub fn import(db: &Database, audio: &AudioStore, data: &[u8]) -> Result<ImportSummary, AppError> {
+    let mut summary = ImportSummary::default();
+    let file = if data.starts_with(b"PK\x03\x04") {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data))
+            .context(AppError::InvalidRequest, "Failed to open bundle")?;
+        // Nothing is stored until the bundle is known to be ours
+        let file = parse(&read_entry(&mut archive, BUNDLE_JSON)?)?;
+        summary.audio_files = restore_audio(&mut archive, &file, audio);
+        file
+    } else {
+        parse(data)?
+    };
+
+    for mut exported in file.conversations {
+        for message in &mut exported.messages {
+            let stored = message.audio_file.as_deref().is_some_and(|file| audio.path(file).is_ok_and(|path| path.exists()));
+            if !stored {
+                message.audio_file = None;
+                message.audio_sha256 = None;
+            }
+        }
+
+        summary.messages += db.import_conversation(&exported.conversation, &exported.messages)?;
+        summary.conversations += 1;
+    }
+
+    println!(
+        "Imported {} conversations with {} messages and {} audio files",
+        summary.conversations, summary.messages, summary.audio_files
+    );
+    Ok(summary)
+}
+
+fn collect(db: &Database, conversation_id: Option<&str>) -> Result<ExportFile, AppError> {
+    let conversations = match conversation_id {
+        Some(id) => vec![db.get_conversation(id)?],
+        None => db.get_conversations(crate::db::ConversationSort::Created, true)?,
+    };
+
+    let mut exported = Vec::new();
+    for conversation in conversations {
+        let messages = db.get_messages(&conversation.id)?;
+        exported.push(ExportedConversation { conversation, messages });
+    }
+
+    Ok(ExportFile {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        conversations: exported,
+    })
+}
+
+fn to_json(file: &ExportFile) -> Result<Vec<u8>, AppError> {
+    serde_json::to_vec_pretty(file).context(AppError::Internal, "Failed to serialize export")
+}
+
+fn to_markdown(exported: &ExportedConversation) -> String {
+    let conversation = &exported.conversation;
+    let mut markdown = format!(
+        "# {}\n\n_Started {}, last activity {}_\n\n",
+        conversation.title.as_deref().unwrap_or("Conversation"),
+        format_timestamp(&conversation.created_at),
+        format_timestamp(&conversation.updated_at),
+    );
+
+    if let Some(summary) = &conversation.summary {
+        markdown.push_str(&format!("> {}\n\n", summary));
+    }
+
+    for message in &exported.messages {
+        let speaker = if message.role == "user" { "You" } else { "Assistant" };
+        markdown.push_str(&format!("**{}** ({}):\n\n", speaker, format_timestamp(&message.timestamp)));
+        match (&message.spoken_content, message.interrupted) {
+            (Some(spoken), true) => markdown.push_str(&format!("{} _(interrupted)_\n\n", spoken)),
+            _ => markdown.push_str(&format!("{}\n\n", message.content)),
+        }
+    }
+
+    markdown
+}
+
+fn format_timestamp(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+fn to_bundle(file: &ExportFile, audio: &AudioStore) -> Result<Vec<u8>, AppError> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let mut add = |name: &str, data: &[u8]| -> Result<(), AppError> {
+        zip.start_file(name, options)
+            .context(AppError::Internal, format!("Failed to add {} to bundle", name))?;
+        zip.write_all(data)
+            .context(AppError::Internal, format!("Failed to add {} to bundle", name))
+    };
+
+    add(BUNDLE_JSON, &to_json(file)?)?;
+    for exported in &file.conversations {
+        add(&format!("transcripts/{}.md", exported.conversation.id), to_markdown(exported).as_bytes())?;
+    }
+
+    // Files are content-addressed, so messages can share one
+    let mut added = Vec::new();
+    for message in file.conversations.iter().flat_map(|exported| &exported.messages) {
+        let Some(name) = &message.audio_file else { continue };
+        if added.contains(name) {
+            continue;
+        }
+        match audio.read(name) {
+            Ok(data) => add(&format!("{}{}", BUNDLE_AUDIO_DIR, name), &data)?,
+            Err(e) => eprintln!("Leaving audio out of bundle: {}", e),
+        }
+        added.push(name.clone());
+    }
+
+    let cursor = zip.finish().context(AppError::Internal, "Failed to finish bundle")?;
+    Ok(cursor.into_inner())
+}
+
+fn parse(json: &[u8]) -> Result<ExportFile, AppError> {
+    let file: ExportFile = serde_json::from_slice(json)
+        .context(AppError::InvalidRequest, "Not a conversation export")?;
+    if file.format != EXPORT_FORMAT {
+        return Err(AppError::InvalidRequest(format!("Unknown export format '{}'", file.format).into()));
+    }
+    if file.version > EXPORT_VERSION {
+        return Err(AppError::InvalidRequest(
+            format!("Export version {} is newer than this app supports ({})", file.version, EXPORT_VERSION).into(),
+        ));
+    }
+    Ok(file)
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Vec<u8>, AppError> {
+    let mut entry = archive
+        .by_name(name)
+        .context(AppError::InvalidRequest, format!("Bundle has no {}", name))?;
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)
+        .context(AppError::InvalidRequest, format!("Failed to read {} from bundle", name))?;
+    Ok(data)
+}
+
+// Copies the audio `file`'s messages refer to from the bundle into the store
+// and returns how many files were stored. Audio that is missing or damaged
+// is skipped; `import` then drops the message's reference to it.
+fn restore_audio(archive: &mut zip::ZipArchive<Cursor<&[u8]>>, file: &ExportFile, audio: &AudioStore) -> usize {
+    let mut names: Vec<&str> = file
+        .conversations
+        .iter()
+        .flat_map(|exported| &exported.messages)
+        .filter_map(|message| message.audio_file.as_deref())
+        .collect();
+    // Files are content-addressed, so messages can share one
+    names.sort_unstable();
+    names.dedup();
+
+    let mut audio_files = 0;
+    for name in names {
+        let wav = match read_entry(archive, &format!("{}{}", BUNDLE_AUDIO_DIR, name)) {
+            Ok(wav) => wav,
+            Err(e) => {
+                eprintln!("Skipping audio {}: {}", name, e);
+                continue;
+            }
+        };
+        // The name is the content hash; anything else was modified or corrupted
+        if audio_store::file_name(&wav) != name {
+            eprintln!("Audio {} in bundle doesn't match its hash", name);
+            continue;
+        }
+        match audio.store_wav(&wav) {
+            Ok(_) => audio_files += 1,
+            Err(e) => eprintln!("Skipping audio {} in bundle: {}", name, e),
+        }
+    }
+    audio_files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Fields = (String, String, String, Option<String>, bool, Option<String>, Option<String>);
+
+    // Everything an import should carry over, i.e. all but the ids
+    fn fields(messages: &[Message]) -> Vec<Fields> {
+        messages
+            .iter()
+            .map(|m| (m.role.clone(), m.content.clone(), m.timestamp.clone(), m.spoken_content.clone(), m.interrupted, m.language.clone(), m.audio_file.clone()))
+            .collect()
+    }
+
+    fn open(dir: &Path) -> (Database, AudioStore) {
+        (Database::open(&dir.join("conversations.db")).unwrap(), AudioStore::new(dir.join("audio")))
+    }
+
+    // A conversation with an interrupted reply and a stored recording
+    fn seed(db: &Database, audio: &AudioStore) -> String {
+        let conversation_id = db.create_conversation().unwrap();
+        let question_id = db.save_message(&conversation_id, "user", "Wie spät ist es?", Some("de")).unwrap();
+        let reply_id = db.save_message(&conversation_id, "assistant", "Es ist drei Uhr. Möchtest du mehr wissen?", Some("de")).unwrap();
+        db.record_interruption(&reply_id, Some("Es ist drei Uhr.")).unwrap();
+        db.rename_conversation(&conversation_id, "Uhrzeit").unwrap();
+
+        let samples: Vec<f32> = (0..1600).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let stored = audio.store_samples(&samples, 16000).unwrap();
+        db.set_message_audio(&question_id, &stored).unwrap();
+        conversation_id
+    }
+
+    fn export_and_read(db: &Database, audio: &AudioStore, format: ExportFormat, dir: &Path) -> Vec<u8> {
+        let path = export(db, audio, None, format, &dir.join("exports")).unwrap();
+        std::fs::read(path).unwrap()
+    }
+
+    fn round_trip(format: ExportFormat) {
+        let source = tempfile::tempdir().unwrap();
+        let (db, audio) = open(source.path());
+        let conversation_id = seed(&db, &audio);
+        let original = db.get_messages(&conversation_id).unwrap();
+        let data = export_and_read(&db, &audio, format, source.path());
+
+        let target = tempfile::tempdir().unwrap();
+        let (imported_db, imported_audio) = open(target.path());
+        let summary = import(&imported_db, &imported_audio, &data).unwrap();
+
+        assert_eq!(summary.conversations, 1);
+        assert_eq!(summary.messages, 2);
+        let conversation = imported_db.get_conversation(&conversation_id).unwrap();
+        assert_eq!(conversation.title.as_deref(), Some("Uhrzeit"));
+        let messages = imported_db.get_messages(&conversation_id).unwrap();
+        assert_eq!(messages.iter().map(|m| &m.id).collect::<Vec<_>>(), original.iter().map(|m| &m.id).collect::<Vec<_>>());
+
+        match format {
+            ExportFormat::Bundle => {
+                assert_eq!(summary.audio_files, 1);
+                assert_eq!(fields(&messages), fields(&original));
+                let file = messages[0].audio_file.as_deref().unwrap();
+                assert_eq!(imported_audio.read(file).unwrap(), audio.read(file).unwrap());
+            }
+            _ => {
+                // The audio isn't in the export or the target store, so the reference is dropped
+                assert_eq!(summary.audio_files, 0);
+                assert_eq!(messages[0].audio_file, None);
+                assert_eq!(fields(&messages[1..]), fields(&original[1..]));
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_json() {
+        round_trip(ExportFormat::Json);
+    }
+
+    #[test]
+    fn round_trips_bundle() {
+        round_trip(ExportFormat::Bundle);
+    }
+
+    #[test]
+    fn import_with_taken_ids_creates_new_ones() {
+        for format in [ExportFormat::Json, ExportFormat::Bundle] {
+            let dir = tempfile::tempdir().unwrap();
+            let (db, audio) = open(dir.path());
+            let conversation_id = seed(&db, &audio);
+            let original = db.get_messages(&conversation_id).unwrap();
+            let data = export_and_read(&db, &audio, format, dir.path());
+
+            let summary = import(&db, &audio, &data).unwrap();
+
+            assert_eq!(summary.messages, 2);
+            let conversations = db.get_conversations(crate::db::ConversationSort::Created, true).unwrap();
+            assert_eq!(conversations.len(), 2);
+            let copy = conversations.iter().find(|c| c.id != conversation_id).unwrap();
+            let copied = db.get_messages(&copy.id).unwrap();
+            assert_eq!(fields(&copied), fields(&original));
+            assert!(copied.iter().all(|m| original.iter().all(|o| o.id != m.id)));
+
+            let untouched = db.get_messages(&conversation_id).unwrap();
+            assert_eq!(untouched.iter().map(|m| &m.id).collect::<Vec<_>>(), original.iter().map(|m| &m.id).collect::<Vec<_>>());
+            assert_eq!(fields(&untouched), fields(&original));
+        }
+    }
+
+    // `bundle` with its conversations.json replaced by `json`
+    fn with_json(bundle: &[u8], json: &[u8]) -> Vec<u8> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bundle)).unwrap();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index).unwrap();
+            let name = entry.name().to_string();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            zip.start_file(name.as_str(), SimpleFileOptions::default()).unwrap();
+            zip.write_all(if name == BUNDLE_JSON { json } else { &data }).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn rejected_bundle_stores_no_audio() {
+        let source = tempfile::tempdir().unwrap();
+        let (db, audio) = open(source.path());
+        seed(&db, &audio);
+        let bundle = export_and_read(&db, &audio, ExportFormat::Bundle, source.path());
+
+        let foreign = br#"{"format":"something-else","version":1,"exported_at":"","conversations":[]}"#;
+        for json in [&b"{ not json"[..], &foreign[..]] {
+            let target = tempfile::tempdir().unwrap();
+            let (imported_db, imported_audio) = open(target.path());
+
+            let result = import(&imported_db, &imported_audio, &with_json(&bundle, json));
+
+            assert!(matches!(result, Err(AppError::InvalidRequest(_))));
+            let stored = std::fs::read_dir(target.path().join("audio")).map(|entries| entries.count()).unwrap_or(0);
+            assert_eq!(stored, 0);
+            assert!(imported_db.get_conversations(crate::db::ConversationSort::Created, true).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, audio) = open(dir.path());
+
+        assert!(matches!(import(&db, &audio, b"{\"hello\":1}"), Err(AppError::InvalidRequest(_))));
+        assert!(matches!(import(&db, &audio, b"PK\x03\x04garbage"), Err(AppError::InvalidRequest(_))));
+    }
+}
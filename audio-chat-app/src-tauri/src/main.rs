@@ -1,45 +1,434 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod error;
 mod db;
 mod asr;
 mod tts;
 mod ollama;
 mod microphone;
 mod audio_processing;
+mod audio_decoder;
+mod streaming_asr;
+mod recorder;
+mod settings;
+mod vad;
+mod chat;
+mod speech_pipeline;
+mod turn;
+mod summaries;
+mod export;
+mod audio_store;
+mod transcript;
+mod vocabulary;
 
-use std::sync::Mutex;
-use std::collections::HashMap;
+use error::{AppError, Context};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Manager;
 
-// State to manage conversations
-#[derive(Default)]
+// Shared backend state, managed by Tauri
 struct AppState {
-    _conversations: Mutex<HashMap<String, Vec<db::Message>>>,
+    asr: Arc<asr::AsrEngine>,
+    transcripts: Arc<streaming_asr::StreamingAsr>,
+    recorder: Arc<recorder::Recorder>,
+    generations: ollama::Generations,
+    turns: Arc<turn::Turns>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        let asr = Arc::new(asr::AsrEngine::default());
+        Self {
+            transcripts: Arc::new(streaming_asr::StreamingAsr::new(asr.clone())),
+            recorder: Arc::new(recorder::Recorder::default()),
+            generations: ollama::Generations::default(),
+            turns: Arc::new(turn::Turns::default()),
+            asr,
+        }
+    }
+}
+
+/// Vocabulary, prompt and replacements for transcribing speech that belongs
+/// to `conversation_id`, if any.
+fn asr_context(
+    conversation_id: Option<&str>,
+    db: &db::Database,
+    settings: &settings::SettingsStore,
+) -> Result<vocabulary::AsrContext, AppError> {
+    vocabulary::AsrContext::load(db, conversation_id, settings.get().asr_conversation_prompt)
+}
+
+#[tauri::command]
+async fn transcribe_audio(
+    audio_path: String,
+    conversation_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+    db: tauri::State<'_, db::Database>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+    app_handle: tauri::AppHandle,
+) -> Result<transcript::Transcript, AppError> {
+    let context = asr_context(conversation_id.as_deref(), &db, &settings)?;
+    asr::transcribe_audio(state.asr.clone(), audio_path, context, app_handle).await
+}
+
+#[tauri::command]
+async fn unload_asr_model(state: tauri::State<'_, AppState>) -> Result<bool, AppError> {
+    // Freeing a large model takes a moment
+    let engine = state.asr.clone();
+    tokio::task::spawn_blocking(move || engine.unload())
+        .await
+        .context(AppError::Internal, "Task failed")
+}
+
+/// The Whisper model registry, marking which models are installed,
+/// selected and loaded.
+#[tauri::command]
+async fn list_whisper_models(
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<asr::WhisperModelInfo>, AppError> {
+    let engine = state.asr.clone();
+    tokio::task::spawn_blocking(move || engine.models(&app_handle))
+        .await
+        .context(AppError::Internal, "Task failed")
+}
+
+/// Selects the Whisper model by registry id and loads it in the background.
+/// The model file has to be installed already.
+#[tauri::command]
+fn select_whisper_model(
+    model: String,
+    state: tauri::State<'_, AppState>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let selected = asr::find_model(&model)
+        .ok_or_else(|| AppError::InvalidRequest(format!("Unknown Whisper model '{}'", model).into()))?;
+    asr::find_model_path(selected, &app_handle)?;
+
+    println!("Selected Whisper model: {}", selected.id);
+    settings.update(|s| s.whisper_model = selected.id.to_string())?;
+
+    let engine = state.asr.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = asr::preload(engine, app_handle).await {
+            eprintln!("Failed to load Whisper model: {}", e);
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn list_asr_profiles(app_handle: tauri::AppHandle) -> Vec<asr::AsrProfileInfo> {
+    asr::profiles(&app_handle)
+}
+
+/// Selects the decoding profile for final transcripts. `custom` replaces the
+/// parameters of the custom profile.
+#[tauri::command]
+fn select_asr_profile(
+    profile: asr::AsrProfile,
+    custom: Option<asr::DecodingOptions>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+) -> Result<(), AppError> {
+    if let Some(custom) = &custom {
+        custom.validate()?;
+    }
+
+    println!("Selected ASR profile: {:?}", profile);
+    settings.update(|s| {
+        s.asr_profile = profile;
+        if let Some(custom) = custom {
+            s.asr_custom_decoding = custom;
+        }
+    })?;
+    Ok(())
+}
+
+#[tauri::command]
+fn start_transcript_stream(sample_rate: u32, state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    state.transcripts.start(sample_rate)
+}
+
+#[tauri::command]
+async fn push_audio_chunk(
+    session_id: String,
+    samples: Vec<f32>,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    state.transcripts.push(&session_id, &samples, &app_handle)
+}
+
+#[tauri::command]
+async fn finish_transcript_stream(
+    session_id: String,
+    conversation_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+    db: tauri::State<'_, db::Database>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+    app_handle: tauri::AppHandle,
+) -> Result<transcript::Transcript, AppError> {
+    let context = asr_context(conversation_id.as_deref(), &db, &settings)?;
+    state.transcripts.finish(&session_id, context, &app_handle)
+        .await
+        .map(|utterance| utterance.transcript)
+}
+
+#[tauri::command]
+fn cancel_transcript_stream(session_id: String, state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    state.transcripts.cancel(&session_id)
+}
+
+#[tauri::command]
+async fn start_recording(
+    hands_free: Option<bool>,
+    state: tauri::State<'_, AppState>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let recorder = state.recorder.clone();
+    let transcripts = state.transcripts.clone();
+    let settings = settings.get();
+    let end_silence_ms = hands_free.unwrap_or(false).then_some(settings.hands_free_silence_ms);
+    tokio::task::spawn_blocking(move || recorder.start(settings.input_device, end_silence_ms, transcripts, app_handle))
+        .await
+        .context(AppError::Internal, "Task failed")?
+}
+
+#[tauri::command]
+async fn stop_recording(
+    conversation_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+    db: tauri::State<'_, db::Database>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+    app_handle: tauri::AppHandle,
+) -> Result<transcript::Transcript, AppError> {
+    let context = asr_context(conversation_id.as_deref(), &db, &settings)?;
+    let recorder = state.recorder.clone();
+    let session_id = tokio::task::spawn_blocking(move || recorder.stop())
+        .await
+        .context(AppError::Internal, "Task failed")??;
+
+    state.transcripts.finish(&session_id, context, &app_handle)
+        .await
+        .map(|utterance| utterance.transcript)
+}
+
+#[tauri::command]
+async fn cancel_recording(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    let recorder = state.recorder.clone();
+    let session_id = tokio::task::spawn_blocking(move || recorder.stop())
+        .await
+        .context(AppError::Internal, "Task failed")??;
+
+    state.transcripts.cancel(&session_id)
+}
+
+#[tauri::command]
+async fn synthesize_speech(
+    text: String,
+    language: Option<String>,
+    tts: tauri::State<'_, tts::Tts>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    tts::synthesize_speech(tts.engine(), text, language, app_handle).await
 }
 
 #[tauri::command]
-async fn transcribe_audio(audio_path: String, app_handle: tauri::AppHandle) -> Result<String, String> {
-    asr::transcribe_audio(audio_path, app_handle).await
+fn set_tts_engine(
+    engine: tts::TtsBackend,
+    voice: Option<String>,
+    piper_model: Option<String>,
+    tts: tauri::State<'_, tts::Tts>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+) -> Result<(), AppError> {
+    let apply = |s: &mut settings::Settings| {
+        s.tts_engine = engine;
+        s.tts_voice = voice.clone();
+        s.piper_model = piper_model.clone();
+    };
+
+    // Only persist a configuration that actually produces an engine
+    let mut updated = settings.get();
+    apply(&mut updated);
+    tts.configure(&updated)?;
+    settings.update(apply)?;
+    Ok(())
 }
 
+/// Sets the voice used for replies in `language` (ISO 639-1), or clears it
+/// with `None`. For Piper the voice is a model path.
 #[tauri::command]
-async fn synthesize_speech(text: String, app_handle: tauri::AppHandle) -> Result<String, String> {
-    tts::synthesize_speech(text, app_handle).await
+fn set_tts_language_voice(
+    language: String,
+    voice: Option<String>,
+    tts: tauri::State<'_, tts::Tts>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+) -> Result<(), AppError> {
+    let apply = |s: &mut settings::Settings| match &voice {
+        Some(voice) => {
+            s.tts_language_voices.insert(language.clone(), voice.clone());
+        }
+        None => {
+            s.tts_language_voices.remove(&language);
+        }
+    };
+
+    let mut updated = settings.get();
+    apply(&mut updated);
+    tts.configure(&updated)?;
+    settings.update(apply)?;
+    Ok(())
 }
 
 #[tauri::command]
-async fn send_prompt(prompt: String) -> Result<String, String> {
+async fn send_prompt(prompt: String) -> Result<String, AppError> {
     ollama::send_prompt(prompt).await
 }
 
+/// Streams the answer as `llm-token` events and returns the full text when
+/// generation ends or is cancelled with `cancel_prompt`.
+#[tauri::command]
+async fn stream_prompt(
+    prompt: String,
+    request_id: String,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ollama::StreamOutcome, AppError> {
+    let cancel = state.generations.register(&request_id)?;
+
+    let result = ollama::stream_prompt(ollama::OLLAMA_URL, prompt, cancel, |token| chat::emit_token(&app_handle, &request_id, token)).await;
+
+    state.generations.finish(&request_id);
+    result
+}
+
+/// Saves the user's message, sends the conversation history to the LLM and
+/// stores the streamed reply. With `speak`, sentences are synthesized as they
+/// complete and announced as `tts-segment` events.
+#[tauri::command]
+async fn send_chat_message(
+    request: chat::ChatRequest,
+    state: tauri::State<'_, AppState>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+    tts: tauri::State<'_, tts::Tts>,
+    app_handle: tauri::AppHandle,
+) -> Result<ollama::StreamOutcome, AppError> {
+    let chat::ChatRequest { conversation_id, content, language, request_id, speak } = request;
+    let cancel = state.generations.register(&request_id)?;
+    let speech = speak
+        .then(|| {
+            speech_pipeline::SpeechQueue::start(
+                request_id.clone(),
+                tts.engine(),
+                language.clone(),
+                cancel.clone(),
+                app_handle.clone(),
+            )
+        });
+
+    let result = chat::respond(
+        &conversation_id,
+        content,
+        chat::ReplyOptions {
+            request_id: &request_id,
+            cancel,
+            speech: speech.as_ref(),
+            token_budget: settings.get().context_token_budget,
+            language: language.as_deref(),
+        },
+        &app_handle,
+    )
+    .await;
+
+    match speech {
+        // Keep the request cancellable until the queued sentences are spoken
+        Some(speech) => {
+            let segments = speech.segments();
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = speech.finish().await {
+                    eprintln!("{}", e);
+                }
+                let state = app_handle.state::<AppState>();
+                state.generations.finish(&request_id);
+                // Typed replies aren't stored, so their clips are only needed for playback
+                state.turns.spent(turn::clip_paths(&segments));
+            });
+        }
+        None => state.generations.finish(&request_id),
+    }
+
+    result.map(|reply| reply.outcome)
+}
+
+/// Runs a whole voice turn (transcription, reply, speech and persistence) for
+/// `audio_data`, or for the active native recording when it is omitted.
+/// Progress is reported as `turn-stage` events; `cancel_turn` aborts it.
+#[tauri::command]
+async fn voice_turn(
+    turn_id: String,
+    conversation_id: String,
+    audio_data: Option<Vec<u8>>,
+    state: tauri::State<'_, AppState>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+    tts: tauri::State<'_, tts::Tts>,
+    app_handle: tauri::AppHandle,
+) -> Result<turn::TurnResult, AppError> {
+    let cancel = state.generations.register(&turn_id)?;
+
+    let services = turn::TurnServices {
+        asr: state.asr.clone(),
+        transcripts: state.transcripts.clone(),
+        recorder: state.recorder.clone(),
+        tts: tts.engine(),
+        settings: settings.get(),
+        turns: state.turns.clone(),
+    };
+    let audio = match audio_data {
+        Some(audio_data) => turn::TurnAudio::Blob(audio_data),
+        None => turn::TurnAudio::Recording,
+    };
+
+    let result = turn::run(turn_id.clone(), conversation_id, audio, services, cancel, app_handle).await;
+    state.generations.finish(&turn_id);
+    result
+}
+
+#[tauri::command]
+fn cancel_turn(turn_id: String, state: tauri::State<'_, AppState>) -> bool {
+    state.generations.cancel(&turn_id)
+}
+
+/// Barge-in: the user started talking over the reply of `turn_id`. Stops the
+/// turn like `cancel_turn` and records that only the first `played_segments`
+/// speech segments were heard.
+#[tauri::command]
+async fn interrupt_turn(
+    turn_id: String,
+    played_segments: usize,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    state.generations.cancel(&turn_id);
+    turn::interrupt(&state.turns, &turn_id, played_segments, &app_handle).await
+}
+
+#[tauri::command]
+fn cancel_prompt(request_id: String, state: tauri::State<'_, AppState>) -> bool {
+    state.generations.cancel(&request_id)
+}
+
 #[tauri::command]
 async fn check_ollama() -> bool {
     ollama::check_ollama().await
 }
 
 #[tauri::command]
-async fn create_conversation(app_handle: tauri::AppHandle) -> Result<String, String> {
-    db::create_conversation(app_handle).await
+async fn create_conversation(db: tauri::State<'_, db::Database>) -> Result<String, AppError> {
+    db.create_conversation()
 }
 
 #[tauri::command]
@@ -47,42 +436,215 @@ async fn save_message(
     conversation_id: String,
     role: String,
     content: String,
+    language: Option<String>,
+    db: tauri::State<'_, db::Database>,
+) -> Result<String, AppError> {
+    db.save_message(&conversation_id, &role, &content, language.as_deref())
+}
+
+/// Lists conversations, pinned first, newest activity first unless `sort`
+/// says otherwise. Archived conversations are left out unless asked for.
+#[tauri::command]
+async fn get_conversations(
+    sort: Option<db::ConversationSort>,
+    include_archived: Option<bool>,
+    db: tauri::State<'_, db::Database>,
+) -> Result<Vec<db::Conversation>, AppError> {
+    db.get_conversations(sort.unwrap_or_default(), include_archived.unwrap_or(false))
+}
+
+/// Full-text search over all messages; see `db::Database::search_messages`.
+#[tauri::command]
+async fn search_messages(
+    query: String,
+    filters: Option<db::SearchFilters>,
+    db: tauri::State<'_, db::Database>,
+) -> Result<Vec<db::SearchHit>, AppError> {
+    db.search_messages(&query, &filters.unwrap_or_default())
+}
+
+/// Exports one conversation, or all of them when `conversation_id` is
+/// omitted, to the Downloads folder. Returns the path of the written file.
+#[tauri::command]
+async fn export_conversations(
+    conversation_id: Option<String>,
+    format: export::ExportFormat,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let dir = match app_handle.path().download_dir() {
+        Ok(dir) => dir,
+        Err(_) => app_handle.path().app_data_dir()
+            .context(AppError::Internal, "Failed to get app data directory")?
+            .join("exports"),
+    };
+
+    // Reads every message and its audio, and zips them for bundles
+    let path = tokio::task::spawn_blocking(move || {
+        export::export(
+            &app_handle.state::<db::Database>(),
+            &app_handle.state::<audio_store::AudioStore>(),
+            conversation_id.as_deref(),
+            format,
+            &dir,
+        )
+    }).await
+    .context(AppError::Internal, "Task failed")??;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Imports a JSON export or bundle created by `export_conversations`.
+#[tauri::command]
+async fn import_conversations(
+    data: Vec<u8>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    db::save_message(conversation_id, role, content, app_handle).await
+) -> Result<export::ImportSummary, AppError> {
+    tokio::task::spawn_blocking(move || {
+        export::import(
+            &app_handle.state::<db::Database>(),
+            &app_handle.state::<audio_store::AudioStore>(),
+            &data,
+        )
+    }).await
+    .context(AppError::Internal, "Task failed")?
+}
+
+#[tauri::command]
+async fn rename_conversation(conversation_id: String, title: String, db: tauri::State<'_, db::Database>) -> Result<(), AppError> {
+    db.rename_conversation(&conversation_id, &title)
+}
+
+#[tauri::command]
+async fn archive_conversation(conversation_id: String, archived: bool, db: tauri::State<'_, db::Database>) -> Result<(), AppError> {
+    db.set_archived(&conversation_id, archived)
 }
 
 #[tauri::command]
-async fn get_conversations(app_handle: tauri::AppHandle) -> Result<Vec<db::Conversation>, String> {
-    db::get_conversations(app_handle).await
+async fn pin_conversation(conversation_id: String, pinned: bool, db: tauri::State<'_, db::Database>) -> Result<(), AppError> {
+    db.set_pinned(&conversation_id, pinned)
 }
 
 #[tauri::command]
-async fn get_messages(conversation_id: String, app_handle: tauri::AppHandle) -> Result<Vec<db::Message>, String> {
-    db::get_messages(conversation_id, app_handle).await
+async fn delete_conversation(
+    conversation_id: String,
+    db: tauri::State<'_, db::Database>,
+    audio: tauri::State<'_, audio_store::AudioStore>,
+) -> Result<(), AppError> {
+    let orphaned = db.delete_conversation(&conversation_id)?;
+    audio.remove(&orphaned);
+    Ok(())
 }
 
 #[tauri::command]
-async fn process_audio_blob(audio_data: Vec<u8>, app_handle: tauri::AppHandle) -> Result<String, String> {
-    // Process the audio blob and convert to proper format
-    let temp_path = audio_processing::process_audio_blob(audio_data)?;
-    
-    // Transcribe the processed audio
-    let transcription = asr::transcribe_audio(temp_path.to_string_lossy().to_string(), app_handle).await?;
-    
-    // Clean up the temporary file
-    if let Err(e) = std::fs::remove_file(&temp_path) {
-        eprintln!("Warning: Failed to clean up temp file {}: {}", temp_path.display(), e);
+async fn get_messages(conversation_id: String, db: tauri::State<'_, db::Database>) -> Result<Vec<db::Message>, AppError> {
+    db.get_messages(&conversation_id)
+}
+
+/// Path of a message's stored audio, for replaying it with `read_audio_file`.
+#[tauri::command]
+fn get_message_audio_path(file: String, audio: tauri::State<'_, audio_store::AudioStore>) -> Result<String, AppError> {
+    let path = audio.path(&file)?;
+    if !path.exists() {
+        return Err(AppError::InvalidRequest(format!("Audio {} is no longer stored", file).into()));
     }
-    
-    Ok(transcription)
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+async fn process_audio_blob(
+    audio_data: Vec<u8>,
+    conversation_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+    db: tauri::State<'_, db::Database>,
+    settings: tauri::State<'_, settings::SettingsStore>,
+    app_handle: tauri::AppHandle,
+) -> Result<transcript::Transcript, AppError> {
+    let context = asr_context(conversation_id.as_deref(), &db, &settings)?;
+    let engine = state.asr.clone();
+    let allow_ffmpeg = settings.get().ffmpeg_fallback;
+
+    tokio::task::spawn_blocking(move || {
+        // Decode the audio blob to 16kHz mono PCM in memory
+        let samples = audio_processing::process_audio_blob(audio_data, allow_ffmpeg)?;
+
+        // Transcribe the processed audio
+        engine.transcribe(&samples, &context, &app_handle)
+    }).await
+    .context(AppError::Internal, "Task failed")?
+}
+
+/// Vocabulary Whisper is primed with: terms for every conversation, plus
+/// those of `conversation_id` when given.
+#[tauri::command]
+async fn get_vocabulary(
+    conversation_id: Option<String>,
+    db: tauri::State<'_, db::Database>,
+) -> Result<Vec<db::VocabularyTerm>, AppError> {
+    db.get_vocabulary(conversation_id.as_deref())
 }
 
+/// Adds a term for every conversation, or only for `conversation_id`.
 #[tauri::command]
-async fn read_audio_file(path: String) -> Result<Vec<u8>, String> {
+async fn add_vocabulary_term(
+    term: String,
+    conversation_id: Option<String>,
+    db: tauri::State<'_, db::Database>,
+) -> Result<(), AppError> {
+    db.add_vocabulary_term(&term, conversation_id.as_deref())
+}
+
+#[tauri::command]
+async fn remove_vocabulary_term(
+    term: String,
+    conversation_id: Option<String>,
+    db: tauri::State<'_, db::Database>,
+) -> Result<(), AppError> {
+    db.remove_vocabulary_term(&term, conversation_id.as_deref())
+}
+
+#[tauri::command]
+async fn get_replacements(db: tauri::State<'_, db::Database>) -> Result<Vec<db::Replacement>, AppError> {
+    db.get_replacements()
+}
+
+/// Makes transcripts read `to` wherever Whisper heard `from` as a whole
+/// word, ignoring case.
+#[tauri::command]
+async fn set_replacement(from: String, to: String, db: tauri::State<'_, db::Database>) -> Result<(), AppError> {
+    db.set_replacement(&from, &to)
+}
+
+#[tauri::command]
+async fn remove_replacement(from: String, db: tauri::State<'_, db::Database>) -> Result<(), AppError> {
+    db.remove_replacement(&from)
+}
+
+#[tauri::command]
+fn set_asr_conversation_prompt(enabled: bool, settings: tauri::State<'_, settings::SettingsStore>) -> Result<(), AppError> {
+    settings.update(|s| s.asr_conversation_prompt = enabled)?;
+    Ok(())
+}
+
+#[tauri::command]
+fn set_ffmpeg_fallback(enabled: bool, settings: tauri::State<'_, settings::SettingsStore>) -> Result<(), AppError> {
+    settings.update(|s| s.ffmpeg_fallback = enabled)?;
+    Ok(())
+}
+
+/// Sets roughly how many tokens of history go to the LLM with each reply.
+#[tauri::command]
+fn set_context_token_budget(tokens: usize, settings: tauri::State<'_, settings::SettingsStore>) -> Result<(), AppError> {
+    if tokens == 0 {
+        return Err(AppError::InvalidRequest("Context token budget must be at least 1".into()));
+    }
+    settings.update(|s| s.context_token_budget = tokens)?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn read_audio_file(path: String) -> Result<Vec<u8>, AppError> {
     println!("Reading audio file: {}", path);
     let data = std::fs::read(&path)
-        .map_err(|e| format!("Failed to read audio file {}: {}", path, e))?;
+        .context(AppError::Internal, format!("Failed to read audio file {}", path))?;
     println!("Read {} bytes from audio file", data.len());
     Ok(data)
 }
@@ -92,28 +654,90 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .manage(AppState::default())
         .setup(|app| {
-            // Initialize database
+            let settings = settings::SettingsStore::load(app.handle());
+            app.manage(tts::Tts::new(&settings.get()));
+            app.manage(settings);
+            microphone::spawn_device_monitor(app.handle().clone());
+            if let Err(e) = tts::remove_leftover_clips(app.handle()) {
+                eprintln!("{}", e);
+            }
+
+            // Open and migrate the database before any command can reach it
+            let database = db::Database::open(&db::database_path(app.handle())?)?;
+            app.manage(database);
+            app.manage(audio_store::AudioStore::open(app.handle())?);
+
+            // Warm up Whisper and unload it again once it has been idle for a while
+            let engine = app.state::<AppState>().asr.clone();
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
-                if let Err(e) = db::init_database(app_handle).await {
-                    eprintln!("Failed to initialize database: {}", e);
+                if let Err(e) = asr::preload(engine.clone(), app_handle).await {
+                    eprintln!("Failed to preload Whisper model: {}", e);
+                }
+
+                let mut interval = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    interval.tick().await;
+                    if engine.unload_if_idle() {
+                        println!("Whisper model unloaded after {:?} idle", engine.idle_timeout());
+                    }
                 }
             });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             transcribe_audio,
+            unload_asr_model,
+            list_whisper_models,
+            select_whisper_model,
+            list_asr_profiles,
+            select_asr_profile,
+            start_transcript_stream,
+            push_audio_chunk,
+            finish_transcript_stream,
+            cancel_transcript_stream,
+            start_recording,
+            stop_recording,
+            cancel_recording,
             synthesize_speech,
+            set_tts_engine,
+            set_tts_language_voice,
             send_prompt,
+            stream_prompt,
+            cancel_prompt,
+            send_chat_message,
+            voice_turn,
+            cancel_turn,
+            interrupt_turn,
             check_ollama,
             create_conversation,
             save_message,
             get_conversations,
+            rename_conversation,
+            archive_conversation,
+            pin_conversation,
+            delete_conversation,
             get_messages,
+            get_message_audio_path,
+            search_messages,
+            export_conversations,
+            import_conversations,
             process_audio_blob,
+            get_vocabulary,
+            add_vocabulary_term,
+            remove_vocabulary_term,
+            get_replacements,
+            set_replacement,
+            remove_replacement,
+            set_asr_conversation_prompt,
+            set_ffmpeg_fallback,
+            set_context_token_budget,
             read_audio_file,
             microphone::request_microphone_permission,
-            microphone::check_microphone_permission
+            microphone::check_microphone_permission,
+            microphone::list_input_devices,
+            microphone::select_input_device,
+            microphone::get_selected_input_device
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
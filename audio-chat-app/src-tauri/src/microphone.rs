@@ -1,17 +1,189 @@
+use crate::error::{AppError, Context, Failure};
+use crate::settings::SettingsStore;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use serde::Serialize;
 use std::sync::mpsc;
 use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+// How often the device list is polled for hot-plug changes
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InputConfigInfo {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    pub is_selected: bool,
+    pub configs: Vec<InputConfigInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDeviceLost {
+    pub name: String,
+    pub fallback: Option<String>,
+}
+
+/// Finds the input device with the given name, falling back to the system
+/// default if it's not connected (or if no name is given).
+pub fn find_input_device(name: Option<&str>) -> Result<cpal::Device, AppError> {
+    let host = cpal::default_host();
+
+    if let Some(name) = name {
+        let found = host.input_devices()
+            .context(AppError::Microphone, "Failed to enumerate input devices")?
+            .find(|device| device.name().map(|n| n == name).unwrap_or(false));
+
+        match found {
+            Some(device) => return Ok(device),
+            None => println!("Input device '{}' not found, falling back to default", name),
+        }
+    }
+
+    host.default_input_device()
+        .ok_or_else(|| AppError::Microphone("No microphone device found".into()))
+}
+
+fn input_device_names() -> Vec<String> {
+    cpal::default_host()
+        .input_devices()
+        .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+fn default_input_device_name() -> Option<String> {
+    cpal::default_host()
+        .default_input_device()
+        .and_then(|device| device.name().ok())
+}
+
+#[tauri::command]
+pub async fn list_input_devices(settings: tauri::State<'_, SettingsStore>) -> Result<Vec<InputDeviceInfo>, AppError> {
+    let selected = settings.get().input_device;
+
+    tokio::task::spawn_blocking(move || {
+        let host = cpal::default_host();
+        let default_name = default_input_device_name();
+
+        let devices = host.input_devices()
+            .context(AppError::Microphone, "Failed to enumerate input devices")?;
+
+        let mut result = Vec::new();
+        for device in devices {
+            let name = match device.name() {
+                Ok(name) => name,
+                Err(e) => {
+                    eprintln!("Skipping input device without a name: {}", e);
+                    continue;
+                }
+            };
+
+            let configs = match device.supported_input_configs() {
+                Ok(configs) => configs
+                    .map(|config| InputConfigInfo {
+                        channels: config.channels(),
+                        min_sample_rate: config.min_sample_rate().0,
+                        max_sample_rate: config.max_sample_rate().0,
+                        sample_format: format!("{:?}", config.sample_format()),
+                    })
+                    .collect(),
+                Err(e) => {
+                    eprintln!("Failed to get configs for {}: {}", name, e);
+                    Vec::new()
+                }
+            };
+
+            result.push(InputDeviceInfo {
+                is_default: default_name.as_deref() == Some(name.as_str()),
+                is_selected: selected.as_deref() == Some(name.as_str()),
+                name,
+                configs,
+            });
+        }
+
+        Ok(result)
+    }).await
+    .context(AppError::Internal, "Task failed")?
+}
+
+/// Selects the microphone to record from; `None` follows the system default.
+#[tauri::command]
+pub async fn select_input_device(name: Option<String>, settings: tauri::State<'_, SettingsStore>) -> Result<(), AppError> {
+    if let Some(name) = &name {
+        let available = tokio::task::spawn_blocking(input_device_names)
+            .await
+            .context(AppError::Internal, "Task failed")?;
+
+        if !available.contains(name) {
+            return Err(AppError::InvalidRequest(format!("Input device '{}' is not connected", name).into()));
+        }
+    }
+
+    println!("Selected input device: {}", name.as_deref().unwrap_or("system default"));
+    settings.update(|s| s.input_device = name)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_selected_input_device(settings: tauri::State<'_, SettingsStore>) -> Option<String> {
+    settings.get().input_device
+}
+
+/// Polls the device list and emits `input-devices-changed` when devices come
+/// or go, plus `input-device-lost` when the selected device disappears. A
+/// recording on a lost device is ended by the recorder, which sees its stream fail.
+pub fn spawn_device_monitor(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut known = input_device_names();
+
+        loop {
+            std::thread::sleep(DEVICE_POLL_INTERVAL);
+
+            let current = input_device_names();
+            if current == known {
+                continue;
+            }
+
+            println!("Input devices changed: {:?}", current);
+            if let Err(e) = app_handle.emit("input-devices-changed", current.clone()) {
+                eprintln!("Failed to emit device change: {}", e);
+            }
+
+            let selected = app_handle.state::<SettingsStore>().get().input_device;
+            if let Some(selected) = selected {
+                if known.contains(&selected) && !current.contains(&selected) {
+                    let lost = InputDeviceLost {
+                        name: selected,
+                        fallback: default_input_device_name(),
+                    };
+                    println!("Selected input device disconnected: {:?}", lost);
+                    if let Err(e) = app_handle.emit("input-device-lost", lost) {
+                        eprintln!("Failed to emit device loss: {}", e);
+                    }
+                }
+            }
+
+            known = current;
+        }
+    });
+}
 
 #[tauri::command]
-pub async fn request_microphone_permission() -> Result<bool, String> {
+pub async fn request_microphone_permission(settings: tauri::State<'_, SettingsStore>) -> Result<bool, AppError> {
     // This function will attempt to access the microphone, which will trigger
     // the macOS permission prompt and make the app appear in Privacy Settings
+    let selected = settings.get().input_device;
     
-    tokio::task::spawn_blocking(|| {
-        let host = cpal::default_host();
-        
-        // Try to get the default input device
-        match host.default_input_device() {
+    tokio::task::spawn_blocking(move || {
+        // Try to get the selected input device
+        match find_input_device(selected.as_deref()).ok() {
             Some(device) => {
                 println!("Found microphone device: {}", device.name().unwrap_or("Unknown".to_string()));
                 
@@ -55,33 +227,33 @@ pub async fn request_microphone_permission() -> Result<bool, String> {
                             }
                             Err(e) => {
                                 eprintln!("Failed to build input stream: {}", e);
-                                Err(format!("Failed to access microphone: {}", e))
+                                Err(AppError::Microphone(Failure::with_source("Failed to access microphone", e)))
                             }
                         }
                     }
                     Err(e) => {
                         eprintln!("Failed to get default input config: {}", e);
-                        Err(format!("Failed to get microphone config: {}", e))
+                        Err(AppError::Microphone(Failure::with_source("Failed to get microphone config", e)))
                     }
                 }
             }
             None => {
                 eprintln!("No input device found");
-                Err("No microphone device found".to_string())
+                Err(AppError::Microphone("No microphone device found".into()))
             }
         }
     }).await
-    .map_err(|e| format!("Task failed: {}", e))?
+    .context(AppError::Internal, "Task failed")?
 }
 
 
 #[tauri::command]
-pub async fn check_microphone_permission() -> Result<bool, String> {
+pub async fn check_microphone_permission(settings: tauri::State<'_, SettingsStore>) -> Result<bool, AppError> {
     // Try to access microphone without triggering permission prompt
-    tokio::task::spawn_blocking(|| {
-        let host = cpal::default_host();
-        
-        match host.default_input_device() {
+    let selected = settings.get().input_device;
+
+    tokio::task::spawn_blocking(move || {
+        match find_input_device(selected.as_deref()).ok() {
             Some(device) => {
                 match device.default_input_config() {
                     Ok(_) => Ok(true),
@@ -91,5 +263,5 @@ pub async fn check_microphone_permission() -> Result<bool, String> {
             None => Ok(false),
         }
     }).await
-    .map_err(|e| format!("Task failed: {}", e))?
+    .context(AppError::Internal, "Task failed")?
 }
\ No newline at end of file
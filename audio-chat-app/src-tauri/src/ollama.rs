@@ -1,7 +1,13 @@
-use reqwest;
+use crate::error::{AppError, Context, Failure};
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::watch;
 
-const OLLAMA_URL: &str = "http://localhost:11434";
+/// Where a local Ollama listens by default.
+pub const OLLAMA_URL: &str = "http://localhost:11434";
+pub const MODEL: &str = "gemma3n:latest";
 
 pub async fn check_ollama() -> bool {
     match reqwest::get(&format!("{}/api/tags", OLLAMA_URL)).await {
@@ -10,39 +16,465 @@ pub async fn check_ollama() -> bool {
     }
 }
 
-pub async fn send_prompt(prompt: String) -> Result<String, String> {
+pub async fn send_prompt(prompt: String) -> Result<String, AppError> {
     let client = reqwest::Client::new();
-    
+
     let payload = json!({
-        "model": "gemma3n:latest",
+        "model": MODEL,
         "prompt": prompt,
         "stream": false
     });
 
     let response = client
-        .post(&format!("{}/api/generate", OLLAMA_URL))
+        .post(format!("{}/api/generate", OLLAMA_URL))
         .json(&payload)
         .send()
         .await
-        .map_err(|e| format!("Failed to send request to Ollama: {}. Make sure Ollama is running with 'ollama run gemma3n:latest'", e))?;
+        .map_err(request_error)?;
 
-    if !response.status().is_success() {
-        return Err(format!("Ollama API returned error: {}. Make sure 'ollama run gemma3n:latest' is running.", response.status()));
-    }
+    check_status(&response)?;
 
     let response_text = response
         .text()
         .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+        .context(AppError::Ollama, "Failed to read response")?;
 
     // Parse the JSON response
     let json_response: Value = serde_json::from_str(&response_text)
-        .map_err(|e| format!("Failed to parse JSON response: {}", e))?;
+        .context(AppError::Ollama, "Failed to parse JSON response")?;
 
     // Extract the response text
     let response_content = json_response["response"]
         .as_str()
-        .ok_or("No response field in Ollama response")?;
+        .ok_or_else(|| AppError::Ollama("No response field in Ollama response".into()))?;
 
     Ok(response_content.to_string())
-}
\ No newline at end of file
+}
+
+/// Non-streaming `/api/chat` request, for short background jobs like titling.
+pub async fn chat(messages: Vec<ChatMessage>) -> Result<String, AppError> {
+    let client = reqwest::Client::new();
+
+    let payload = json!({
+        "model": MODEL,
+        "messages": messages,
+        "stream": false
+    });
+
+    let response = client
+        .post(format!("{}/api/chat", OLLAMA_URL))
+        .json(&payload)
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    check_status(&response)?;
+
+    let json_response: Value = response
+        .json()
+        .await
+        .context(AppError::Ollama, "Failed to parse JSON response")?;
+
+    json_response["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::Ollama("No message in Ollama response".into()))
+}
+
+// Connection failures mean Ollama isn't running; anything else is a failed request
+fn request_error(e: reqwest::Error) -> AppError {
+    if e.is_connect() || e.is_timeout() {
+        AppError::OllamaUnavailable(Failure::with_source("Failed to send request to Ollama", e))
+    } else {
+        AppError::Ollama(Failure::with_source("Failed to send request to Ollama", e))
+    }
+}
+
+fn check_status(response: &reqwest::Response) -> Result<(), AppError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let message = if status == reqwest::StatusCode::NOT_FOUND {
+        format!("Ollama API returned {}. Pull the model with 'ollama pull {}'.", status, MODEL)
+    } else {
+        format!("Ollama API returned error: {}", status)
+    };
+    Err(AppError::Ollama(message.into()))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenEvent {
+    pub request_id: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamOutcome {
+    pub text: String,
+    pub cancelled: bool,
+    /// Token counts Ollama reports with the final chunk; unknown if cancelled.
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+}
+
+/// Tracks in-flight streaming generations so they can be cancelled by id.
+#[derive(Default)]
+pub struct Generations {
+    active: Mutex<HashMap<String, watch::Sender<bool>>>,
+}
+
+impl Generations {
+    pub fn register(&self, request_id: &str) -> Result<watch::Receiver<bool>, AppError> {
+        let (tx, rx) = watch::channel(false);
+        let mut active = self.active.lock()
+            .map_err(|_| AppError::Internal("Generation registry lock poisoned".into()))?;
+
+        if active.contains_key(request_id) {
+            return Err(AppError::InvalidRequest(format!("Generation {} is already running", request_id).into()));
+        }
+
+        active.insert(request_id.to_string(), tx);
+        Ok(rx)
+    }
+
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.active.lock() {
+            Ok(active) => active
+                .get(request_id)
+                .map(|tx| tx.send(true).is_ok())
+                .unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    pub fn finish(&self, request_id: &str) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(request_id);
+        }
+    }
+}
+
+/// Splits a byte stream into newline-delimited JSON values. Chunks from the
+/// network can end mid-line (or mid-character), so partial lines are kept
+/// until the rest arrives.
+#[derive(Default)]
+pub struct NdjsonDecoder {
+    buffer: Vec<u8>,
+}
+
+impl NdjsonDecoder {
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Value>, AppError> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut values = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            if let Some(value) = parse_line(&line)? {
+                values.push(value);
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Parses whatever is left once the stream has closed.
+    pub fn finish(&mut self) -> Result<Option<Value>, AppError> {
+        let line = std::mem::take(&mut self.buffer);
+        parse_line(&line)
+    }
+}
+
+fn parse_line(line: &[u8]) -> Result<Option<Value>, AppError> {
+    let line = std::str::from_utf8(line)
+        .context(AppError::Ollama, "Ollama sent invalid UTF-8")?
+        .trim();
+
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    serde_json::from_str(line)
+        .map(Some)
+        .context(AppError::Ollama, "Failed to parse streamed JSON")
+}
+
+/// Streams a completion for `prompt` from the Ollama at `base_url`, calling
+/// `on_token` for each piece of text as it is generated. Cancelling via
+/// `cancel` stops reading and drops the connection, which makes Ollama abort
+/// the generation.
+pub async fn stream_prompt<F>(
+    base_url: &str,
+    prompt: String,
+    cancel: watch::Receiver<bool>,
+    on_token: F,
+) -> Result<StreamOutcome, AppError>
+where
+    F: FnMut(&str),
+{
+    let payload = json!({
+        "model": MODEL,
+        "prompt": prompt,
+        "stream": true
+    });
+
+    stream_completion(base_url, "/api/generate", payload, cancel, on_token).await
+}
+
+/// Streams a reply to a multi-turn conversation via `/api/chat`.
+pub async fn stream_chat<F>(
+    base_url: &str,
+    messages: Vec<ChatMessage>,
+    cancel: watch::Receiver<bool>,
+    on_token: F,
+) -> Result<StreamOutcome, AppError>
+where
+    F: FnMut(&str),
+{
+    let payload = json!({
+        "model": MODEL,
+        "messages": messages,
+        "stream": true
+    });
+
+    stream_completion(base_url, "/api/chat", payload, cancel, on_token).await
+}
+
+async fn stream_completion<F>(
+    base_url: &str,
+    path: &str,
+    payload: Value,
+    mut cancel: watch::Receiver<bool>,
+    mut on_token: F,
+) -> Result<StreamOutcome, AppError>
+where
+    F: FnMut(&str),
+{
+    let client = reqwest::Client::new();
+
+    let mut response = client
+        .post(format!("{}{}", base_url, path))
+        .json(&payload)
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    check_status(&response)?;
+
+    let mut decoder = NdjsonDecoder::default();
+    let mut text = String::new();
+    let mut cancellable = true;
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = response.chunk() => chunk.context(AppError::Ollama, "Failed to read response")?,
+            changed = cancel.changed(), if cancellable => {
+                match changed {
+                    Ok(()) if *cancel.borrow() => {
+                        println!("Generation cancelled after {} characters", text.len());
+                        return Ok(StreamOutcome { text, cancelled: true, prompt_tokens: None, completion_tokens: None });
+                    }
+                    Ok(()) => {}
+                    // Nobody can cancel us any more
+                    Err(_) => cancellable = false,
+                }
+                continue;
+            }
+        };
+
+        let finished = chunk.is_none();
+        let values = match chunk {
+            Some(chunk) => decoder.push(&chunk)?,
+            None => decoder.finish()?.into_iter().collect(),
+        };
+
+        for value in &values {
+            if let Some(error) = value["error"].as_str() {
+                return Err(AppError::Ollama(format!("Ollama returned an error: {}", error).into()));
+            }
+
+            // /api/generate puts text in `response`, /api/chat in `message.content`
+            let token = value["response"]
+                .as_str()
+                .or_else(|| value["message"]["content"].as_str())
+                .unwrap_or("");
+
+            if !token.is_empty() {
+                text.push_str(token);
+                on_token(token);
+            }
+
+            if value["done"].as_bool().unwrap_or(false) {
+                return Ok(StreamOutcome {
+                    text,
+                    cancelled: false,
+                    prompt_tokens: value["prompt_eval_count"].as_u64(),
+                    completion_tokens: value["eval_count"].as_u64(),
+                });
+            }
+        }
+
+        if finished {
+            return Err(AppError::Ollama("Ollama closed the stream before finishing".into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Stand-in for Ollama: answers one request with `chunks` as separate
+    // HTTP chunks, then keeps the connection open for `linger`
+    async fn serve(chunks: Vec<Vec<u8>>, linger: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_request(&mut socket).await;
+
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\n\r\n")
+                .await
+                .unwrap();
+            for chunk in chunks {
+                socket.write_all(format!("{:x}\r\n", chunk.len()).as_bytes()).await.unwrap();
+                socket.write_all(&chunk).await.unwrap();
+                socket.write_all(b"\r\n").await.unwrap();
+                socket.flush().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            tokio::time::sleep(linger).await;
+            let _ = socket.write_all(b"0\r\n\r\n").await;
+        });
+
+        base_url
+    }
+
+    async fn read_request(socket: &mut tokio::net::TcpStream) {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text[..end]
+                    .lines()
+                    .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                if request.len() >= end + 4 + length {
+                    return;
+                }
+            }
+            if n == 0 {
+                return;
+            }
+        }
+    }
+
+    fn chat_line(content: &str) -> String {
+        format!("{}\n", json!({ "message": { "role": "assistant", "content": content }, "done": false }))
+    }
+
+    #[tokio::test]
+    async fn streams_tokens_split_across_chunks() {
+        let body = [
+            chat_line("Hel"),
+            chat_line("lo "),
+            chat_line("wörld"),
+            format!("{}\n", json!({ "message": { "role": "assistant", "content": "" }, "done": true, "prompt_eval_count": 12, "eval_count": 3 })),
+        ]
+        .concat()
+        .into_bytes();
+        let first_line = chat_line("Hel").len();
+        let umlaut = body.windows(2).position(|pair| pair == "ö".as_bytes()).unwrap();
+        // Mid-line, then between the two bytes of "ö"
+        let chunks = vec![
+            body[..first_line + 5].to_vec(),
+            body[first_line + 5..umlaut + 1].to_vec(),
+            body[umlaut + 1..].to_vec(),
+        ];
+        let base_url = serve(chunks, Duration::ZERO).await;
+        let (_cancel_tx, cancel) = watch::channel(false);
+
+        let mut tokens = Vec::new();
+        let outcome = stream_chat(&base_url, vec![ChatMessage::new("user", "Hi")], cancel, |token| tokens.push(token.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(tokens, ["Hel", "lo ", "wörld"]);
+        assert_eq!(outcome.text, "Hello wörld");
+        assert!(!outcome.cancelled);
+        assert_eq!(outcome.prompt_tokens, Some(12));
+        assert_eq!(outcome.completion_tokens, Some(3));
+    }
+
+    #[tokio::test]
+    async fn reports_streamed_errors() {
+        let chunks = vec![
+            chat_line("Hi").into_bytes(),
+            b"{\"error\":\"model 'gemma3n:latest' not found\"}\n".to_vec(),
+        ];
+        let base_url = serve(chunks, Duration::ZERO).await;
+        let (_cancel_tx, cancel) = watch::channel(false);
+
+        let result = stream_prompt(&base_url, "Hi".to_string(), cancel, |_| {}).await;
+
+        match result {
+            Err(AppError::Ollama(failure)) => assert!(format!("{:?}", failure).contains("not found")),
+            other => panic!("expected an Ollama error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_when_stream_ends_early() {
+        let base_url = serve(vec![chat_line("Hi").into_bytes()], Duration::ZERO).await;
+        let (_cancel_tx, cancel) = watch::channel(false);
+
+        let result = stream_chat(&base_url, vec![ChatMessage::new("user", "Hi")], cancel, |_| {}).await;
+
+        assert!(matches!(result, Err(AppError::Ollama(_))));
+    }
+
+    #[tokio::test]
+    async fn stops_when_cancelled() {
+        // The server never finishes; only cancelling ends the stream
+        let base_url = serve(vec![chat_line("Partial").into_bytes()], Duration::from_secs(30)).await;
+        let (cancel_tx, cancel) = watch::channel(false);
+
+        let outcome = tokio::time::timeout(
+            Duration::from_secs(5),
+            stream_chat(&base_url, vec![ChatMessage::new("user", "Hi")], cancel, |_| {
+                cancel_tx.send(true).unwrap();
+            }),
+        )
+        .await
+        .expect("cancelling should end the stream")
+        .unwrap();
+
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.text, "Partial");
+        assert_eq!(outcome.prompt_tokens, None);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::error::{AppError, Context, Failure};
+use crate::microphone;
+use crate::streaming_asr::{StreamingAsr, TranscriptEvent};
+use crate::vad;
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{FromSample, SizedSample};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::Emitter;
+
+// How much unread audio the capture callback may queue before old samples are dropped
+const RING_BUFFER_SECONDS: usize = 10;
+// How often the capture thread hands buffered audio to the ASR pipeline
+const DRAIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Fixed-capacity buffer between the cpal callback and the ASR pipeline.
+/// When full, the oldest samples are overwritten.
+pub struct RingBuffer {
+    data: Vec<f32>,
+    start: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            data: vec![0.0; capacity.max(1)],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// Appends samples and returns how many old samples had to be dropped.
+    pub fn push(&mut self, samples: &[f32]) -> usize {
+        let capacity = self.data.len();
+        let mut dropped = 0;
+
+        for &sample in samples {
+            let end = (self.start + self.len) % capacity;
+            self.data[end] = sample;
+
+            if self.len == capacity {
+                self.start = (self.start + 1) % capacity;
+                dropped += 1;
+            } else {
+                self.len += 1;
+            }
+        }
+
+        dropped
+    }
+
+    /// Removes and returns everything buffered, oldest first.
+    pub fn drain(&mut self) -> Vec<f32> {
+        let capacity = self.data.len();
+        let samples = (0..self.len)
+            .map(|i| self.data[(self.start + i) % capacity])
+            .collect();
+
+        self.start = 0;
+        self.len = 0;
+        samples
+    }
+}
+
+struct ActiveRecording {
+    session_id: String,
+    stop_tx: mpsc::Sender<()>,
+    thread: JoinHandle<Result<(), AppError>>,
+}
+
+/// Records from the microphone with cpal and streams PCM directly into the
+/// transcription pipeline, without going through the webview.
+#[derive(Default)]
+pub struct Recorder {
+    active: Mutex<Option<ActiveRecording>>,
+}
+
+impl Recorder {
+    /// Opens the input stream and starts feeding a new transcript stream.
+    /// Returns the transcript session id so partial events can be matched.
+    ///
+    /// With `end_silence_ms` set (hands-free mode), `speech-started` is
+    /// emitted when the user starts talking and `utterance-ended` once they
+    /// stop talking for that long.
+    ///
+    /// If the microphone fails mid-recording (e.g. it is unplugged), capture
+    /// ends, `recording-failed` is emitted and `stop` returns the error.
+    pub fn start(
+        &self,
+        device_name: Option<String>,
+        end_silence_ms: Option<usize>,
+        transcripts: Arc<StreamingAsr>,
+        app_handle: tauri::AppHandle,
+    ) -> Result<String, AppError> {
+        let mut active = self.active.lock()
+            .map_err(|_| AppError::Internal("Recorder lock poisoned".into()))?;
+
+        if active.is_some() {
+            return Err(AppError::InvalidRequest("Recording is already in progress".into()));
+        }
+
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<u32, AppError>>();
+        let (session_tx, session_rx) = mpsc::channel::<String>();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        // cpal streams aren't Send, so the stream lives on its own thread for its whole lifetime
+        let pipeline = transcripts.clone();
+        let thread = std::thread::spawn(move || {
+            let (error_tx, error_rx) = mpsc::channel::<cpal::StreamError>();
+            let (stream, buffer, sample_rate) = match open_input_stream(device_name.as_deref(), error_tx) {
+                Ok((stream, buffer, sample_rate)) => {
+                    let _ = ready_tx.send(Ok(sample_rate));
+                    (stream, buffer, sample_rate)
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return Ok(());
+                }
+            };
+
+            let session_id = match session_rx.recv() {
+                Ok(session_id) => session_id,
+                Err(_) => return Ok(()),
+            };
+
+            let mut endpoint = end_silence_ms
+                .map(|ms| vad::EndpointDetector::new(sample_rate, ms, vad::VadConfig::default()));
+            let mut speech_reported = false;
+
+            // Runs until stop is requested, the recorder is dropped or the stream fails
+            let mut failure = None;
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(DRAIN_INTERVAL) {
+                if let Ok(e) = error_rx.try_recv() {
+                    failure = Some(e);
+                    break;
+                }
+
+                let samples = drain_samples(&buffer);
+                if let Some(detector) = endpoint.as_mut() {
+                    let ended = detector.push(&samples);
+
+                    // Lets the frontend barge in on a reply that is still playing
+                    if !speech_reported && detector.speech_started() {
+                        speech_reported = true;
+                        let event = TranscriptEvent { session_id: session_id.clone(), text: String::new(), language: None };
+                        if let Err(e) = app_handle.emit("speech-started", event) {
+                            eprintln!("Failed to emit start of speech: {}", e);
+                        }
+                    }
+
+                    if ended {
+                        println!("End of utterance detected for transcript stream {}", session_id);
+                        let event = TranscriptEvent { session_id: session_id.clone(), text: String::new(), language: None };
+                        if let Err(e) = app_handle.emit("utterance-ended", event) {
+                            eprintln!("Failed to emit end of utterance: {}", e);
+                        }
+                        // Only report the first endpoint; the frontend stops the recording
+                        endpoint = None;
+                    }
+                }
+                forward_samples(&samples, &pipeline, &session_id, &app_handle);
+            }
+
+            drop(stream);
+            if let Some(e) = failure {
+                eprintln!("Microphone failed during transcript stream {}: {}", session_id, e);
+                let _ = pipeline.cancel(&session_id);
+                let event = TranscriptEvent { session_id: session_id.clone(), text: String::new(), language: None };
+                if let Err(e) = app_handle.emit("recording-failed", event) {
+                    eprintln!("Failed to emit recording failure: {}", e);
+                }
+
+                let message = match e {
+                    cpal::StreamError::DeviceNotAvailable => "The microphone was disconnected while recording",
+                    _ => "The microphone stopped while recording",
+                };
+                return Err(AppError::Microphone(Failure::with_source(message, e)));
+            }
+
+            forward_samples(&drain_samples(&buffer), &pipeline, &session_id, &app_handle);
+            println!("Stopped recording for transcript stream {}", session_id);
+            Ok(())
+        });
+
+        let sample_rate = ready_rx.recv()
+            .map_err(|_| AppError::Microphone("Recording thread exited unexpectedly".into()))??;
+
+        let session_id = match transcripts.start(sample_rate) {
+            Ok(session_id) => session_id,
+            Err(e) => {
+                // Dropping the sender makes the capture thread close the stream and exit
+                drop(session_tx);
+                let _ = thread.join();
+                return Err(e);
+            }
+        };
+
+        session_tx.send(session_id.clone())
+            .map_err(|_| AppError::Microphone("Recording thread exited unexpectedly".into()))?;
+
+        *active = Some(ActiveRecording {
+            session_id: session_id.clone(),
+            stop_tx,
+            thread,
+        });
+
+        Ok(session_id)
+    }
+
+    /// Stops capture and returns the session id whose audio is now complete,
+    /// or the error that ended capture early.
+    pub fn stop(&self) -> Result<String, AppError> {
+        let recording = self.active.lock()
+            .map_err(|_| AppError::Internal("Recorder lock poisoned".into()))?
+            .take()
+            .ok_or_else(|| AppError::InvalidRequest("No recording in progress".into()))?;
+
+        let _ = recording.stop_tx.send(());
+        recording.thread.join()
+            .map_err(|_| AppError::Internal("Recording thread panicked".into()))??;
+
+        Ok(recording.session_id)
+    }
+}
+
+fn open_input_stream(
+    device_name: Option<&str>,
+    error_tx: mpsc::Sender<cpal::StreamError>,
+) -> Result<(cpal::Stream, Arc<Mutex<RingBuffer>>, u32), AppError> {
+    let device = microphone::find_input_device(device_name)?;
+
+    let config = device.default_input_config()
+        .context(AppError::Microphone, "Failed to get microphone config")?;
+
+    println!(
+        "Recording from {} ({:?})",
+        device.name().unwrap_or("Unknown".to_string()),
+        config
+    );
+
+    let sample_rate = config.sample_rate().0;
+    let channels = config.channels() as usize;
+    let sample_format = config.sample_format();
+    let stream_config: cpal::StreamConfig = config.into();
+    let buffer = Arc::new(Mutex::new(RingBuffer::with_capacity(sample_rate as usize * RING_BUFFER_SECONDS)));
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, channels, buffer.clone(), error_tx),
+        cpal::SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, channels, buffer.clone(), error_tx),
+        cpal::SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, channels, buffer.clone(), error_tx),
+        format => return Err(AppError::Microphone(format!("Unsupported microphone sample format: {:?}", format).into())),
+    }?;
+
+    stream.play()
+        .context(AppError::Microphone, "Failed to start microphone stream")?;
+
+    Ok((stream, buffer, sample_rate))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    buffer: Arc<Mutex<RingBuffer>>,
+    error_tx: mpsc::Sender<cpal::StreamError>,
+) -> Result<cpal::Stream, AppError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            // Downmix interleaved frames to mono
+            let mono: Vec<f32> = data
+                .chunks(channels.max(1))
+                .map(|frame| frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / frame.len() as f32)
+                .collect();
+
+            if let Ok(mut buffer) = buffer.lock() {
+                let dropped = buffer.push(&mono);
+                if dropped > 0 {
+                    eprintln!("Recorder buffer overflow, dropped {} samples", dropped);
+                }
+            }
+        },
+        // The capture thread ends the recording when the stream fails
+        move |err| {
+            let _ = error_tx.send(err);
+        },
+        None,
+    ).context(AppError::Microphone, "Failed to access microphone")
+}
+
+fn drain_samples(buffer: &Mutex<RingBuffer>) -> Vec<f32> {
+    buffer.lock().map(|mut buffer| buffer.drain()).unwrap_or_default()
+}
+
+fn forward_samples(
+    samples: &[f32],
+    transcripts: &StreamingAsr,
+    session_id: &str,
+    app_handle: &tauri::AppHandle,
+) {
+    if samples.is_empty() {
+        return;
+    }
+
+    if let Err(e) = transcripts.push(session_id, samples, app_handle) {
+        eprintln!("Failed to forward recorded audio: {}", e);
+    }
+}
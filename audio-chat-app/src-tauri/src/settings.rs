@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::asr;
+use crate::error::{AppError, Context};
+use crate::tts::TtsBackend;
+
+/// User preferences persisted as JSON in the app data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Name of the preferred microphone; `None` follows the system default.
+    pub input_device: Option<String>,
+    /// Allow shelling out to ffmpeg for formats the built-in decoder can't handle.
+    pub ffmpeg_fallback: bool,
+    /// In hands-free mode, how much trailing silence ends an utterance.
+    pub hands_free_silence_ms: usize,
+    /// Approximate number of tokens of conversation history sent to the LLM;
+    /// set with `set_context_token_budget`.
+    pub context_token_budget: usize,
+    /// Speech synthesizer for replies.
+    pub tts_engine: TtsBackend,
+    /// Voice name for `say`/espeak-ng, or the English MeloTTS speaker (e.g. "EN-US").
+    pub tts_voice: Option<String>,
+    /// Path to the `.onnx` voice model used by Piper.
+    pub piper_model: Option<String>,
+    /// Voice (Piper: model path) for replies in other languages, keyed by
+    /// ISO 639-1 code, e.g. `{"de": "Anna"}`.
+    pub tts_language_voices: HashMap<String, String>,
+    /// Id of the Whisper model in `asr::MODELS`.
+    pub whisper_model: String,
+    /// Prime Whisper with the end of the conversation, not just the vocabulary.
+    pub asr_conversation_prompt: bool,
+    /// Decoding parameters used for final transcripts.
+    pub asr_profile: asr::AsrProfile,
+    /// Parameters of the `custom` profile.
+    pub asr_custom_decoding: asr::DecodingOptions,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            input_device: None,
+            ffmpeg_fallback: false,
+            hands_free_silence_ms: 800,
+            context_token_budget: 4000,
+            tts_engine: TtsBackend::Auto,
+            tts_voice: None,
+            piper_model: None,
+            tts_language_voices: HashMap::new(),
+            whisper_model: asr::DEFAULT_MODEL.to_string(),
+            asr_conversation_prompt: true,
+            asr_profile: asr::AsrProfile::default(),
+            asr_custom_decoding: asr::DecodingOptions::default(),
+        }
+    }
+}
+
+pub struct SettingsStore {
+    settings: Mutex<Settings>,
+    path: Option<PathBuf>,
+}
+
+impl SettingsStore {
+    /// Loads settings from disk, falling back to defaults if the file is
+    /// missing or unreadable.
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        let path = match get_settings_path(app_handle) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("Settings will not be persisted: {}", e);
+                None
+            }
+        };
+
+        let settings = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| match std::fs::read_to_string(path) {
+                Ok(contents) => serde_json::from_str(&contents)
+                    .map_err(|e| eprintln!("Failed to parse settings, using defaults: {}", e))
+                    .ok(),
+                Err(e) => {
+                    eprintln!("Failed to read settings, using defaults: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            settings: Mutex::new(settings),
+            path,
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Applies a change and writes the result to disk. If the write fails the
+    /// current settings are left as they were.
+    pub fn update<F>(&self, f: F) -> Result<Settings, AppError>
+    where
+        F: FnOnce(&mut Settings),
+    {
+        let mut settings = self.settings.lock()
+            .map_err(|_| AppError::Internal("Settings lock poisoned".into()))?;
+        let mut updated = settings.clone();
+        f(&mut updated);
+
+        // Only take the change once it's on disk, so a failed write leaves nothing half-applied
+        if let Some(path) = &self.path {
+            let contents = serde_json::to_string_pretty(&updated)
+                .context(AppError::Internal, "Failed to serialize settings")?;
+            std::fs::write(path, contents)
+                .context(AppError::Internal, "Failed to write settings")?;
+        }
+
+        *settings = updated.clone();
+        Ok(updated)
+    }
+}
+
+fn get_settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .context(AppError::Internal, "Failed to get app data directory")?;
+
+    std::fs::create_dir_all(&app_data_dir)
+        .context(AppError::Internal, "Failed to create app data directory")?;
+
+    Ok(app_data_dir.join("settings.json"))
+}
@@ -0,0 +1,201 @@
+use crate::error::{AppError, Context};
+use crate::tts::{self, TtsEngine};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use tauri::Emitter;
+use tokio::sync::{mpsc, watch};
+
+// Fragments shorter than this are held back and spoken with the next sentence
+const MIN_SENTENCE_CHARS: usize = 20;
+const ABBREVIATIONS: &[&str] = &["mr", "mrs", "ms", "dr", "prof", "st", "vs", "etc", "e.g", "i.e", "approx"];
+
+/// Cuts streamed LLM text into sentences as soon as they are complete.
+#[derive(Default)]
+pub struct SentenceSplitter {
+    buffer: String,
+}
+
+impl SentenceSplitter {
+    pub fn push(&mut self, token: &str) -> Vec<String> {
+        self.buffer.push_str(token);
+
+        let mut sentences = Vec::new();
+        while let Some(end) = find_boundary(&self.buffer) {
+            let sentence = self.buffer[..end].trim().to_string();
+            self.buffer.drain(..end);
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+        }
+
+        sentences
+    }
+
+    /// Returns whatever is left once the LLM has finished.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buffer);
+        let rest = rest.trim();
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+}
+
+// A boundary is sentence punctuation followed by whitespace (so "3.14" and a
+// trailing "." that might still continue aren't split), or a newline.
+fn find_boundary(text: &str) -> Option<usize> {
+    for (i, c) in text.char_indices() {
+        let end = match c {
+            '\n' => i + 1,
+            '.' | '!' | '?' | '…' => {
+                // Keep closing quotes and brackets with the sentence they end
+                let mut end = i + c.len_utf8();
+                let mut rest = text[end..].chars();
+                let mut next = rest.next();
+                while let Some(closer @ ('"' | '\'' | ')' | ']' | '”' | '’')) = next {
+                    end += closer.len_utf8();
+                    next = rest.next();
+                }
+
+                if !next.is_some_and(char::is_whitespace) || (c == '.' && is_abbreviation(&text[..i])) {
+                    continue;
+                }
+                end
+            }
+            _ => continue,
+        };
+
+        if text[..end].trim().chars().count() >= MIN_SENTENCE_CHARS {
+            return Some(end);
+        }
+    }
+
+    None
+}
+
+fn is_abbreviation(before_dot: &str) -> bool {
+    let word = before_dot
+        .rsplit(|c: char| c.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+
+    // Single letters are usually initials ("J. R. R. Tolkien")
+    ABBREVIATIONS.contains(&word.as_str()) || (word.chars().count() == 1 && word.chars().all(char::is_alphabetic))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechSegmentEvent {
+    pub request_id: String,
+    pub index: usize,
+    pub text: String,
+    pub path: String,
+}
+
+/// A sentence that was synthesized, and the clip it was synthesized to.
+#[derive(Debug, Clone)]
+pub struct SpokenSegment {
+    pub text: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechFinishedEvent {
+    pub request_id: String,
+    pub segments: usize,
+}
+
+/// Synthesizes sentences in order on a background task while the LLM keeps
+/// generating. Each finished clip is announced with a `tts-segment` event;
+/// `tts-finished` follows the last one. Once `cancel` fires, sentences that
+/// haven't been synthesized yet are dropped.
+/// Sentences are spoken with the voice configured for `language`.
+pub struct SpeechQueue {
+    tx: mpsc::UnboundedSender<String>,
+    worker: tauri::async_runtime::JoinHandle<usize>,
+    segments: Arc<Mutex<Vec<SpokenSegment>>>,
+}
+
+impl SpeechQueue {
+    pub fn start(
+        request_id: String,
+        engine: Arc<dyn TtsEngine>,
+        language: Option<String>,
+        mut cancel: watch::Receiver<bool>,
+        app_handle: tauri::AppHandle,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let segments = Arc::new(Mutex::new(Vec::new()));
+
+        let synthesized = segments.clone();
+        let worker = tauri::async_runtime::spawn(async move {
+            let mut index = 0;
+            let mut cancellable = true;
+            loop {
+                let text = tokio::select! {
+                    text = rx.recv() => match text {
+                        Some(text) => text,
+                        None => break,
+                    },
+                    changed = cancel.changed(), if cancellable => {
+                        // A dropped sender just means nobody can cancel any more
+                        cancellable = changed.is_ok();
+                        if !*cancel.borrow() {
+                            continue;
+                        }
+                        String::new()
+                    }
+                };
+
+                if *cancel.borrow() {
+                    println!("Speech for {} cancelled after {} segments", request_id, index);
+                    break;
+                }
+
+                match tts::synthesize_speech(engine.clone(), text.clone(), language.clone(), app_handle.clone()).await {
+                    Ok(path) => {
+                        if let Ok(mut synthesized) = synthesized.lock() {
+                            synthesized.push(SpokenSegment { text: text.clone(), path: path.clone() });
+                        }
+                        let event = SpeechSegmentEvent {
+                            request_id: request_id.clone(),
+                            index,
+                            text,
+                            path,
+                        };
+                        if let Err(e) = app_handle.emit("tts-segment", event) {
+                            eprintln!("Failed to emit speech segment: {}", e);
+                        }
+                        index += 1;
+                    }
+                    // Skip the sentence rather than silencing the rest of the answer
+                    Err(e) => eprintln!("Failed to synthesize sentence '{}': {}", text, e),
+                }
+            }
+
+            let event = SpeechFinishedEvent { request_id, segments: index };
+            if let Err(e) = app_handle.emit("tts-finished", event) {
+                eprintln!("Failed to emit speech completion: {}", e);
+            }
+
+            index
+        });
+
+        Self { tx, worker, segments }
+    }
+
+    /// Segments synthesized so far, in `tts-segment` index order.
+    pub fn segments(&self) -> Arc<Mutex<Vec<SpokenSegment>>> {
+        self.segments.clone()
+    }
+
+    pub fn push(&self, sentence: String) {
+        if self.tx.send(sentence).is_err() {
+            eprintln!("Speech queue closed, dropping sentence");
+        }
+    }
+
+    /// Waits for every queued sentence to be synthesized.
+    pub async fn finish(self) -> Result<usize, AppError> {
+        drop(self.tx);
+        self.worker.await.context(AppError::Internal, "Speech task failed")
+    }
+}
@@ -0,0 +1,212 @@
+use crate::asr::{AsrEngine, Utterance, SAMPLE_RATE};
+use crate::audio_processing::StreamResampler;
+use crate::error::{AppError, Context};
+use crate::vad;
+use crate::vocabulary::AsrContext;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+
+// Re-run Whisper once this much new audio has arrived
+const PARTIAL_STEP_SAMPLES: usize = SAMPLE_RATE as usize;
+// Whisper warns and produces garbage on inputs shorter than a second
+const MIN_WINDOW_SAMPLES: usize = SAMPLE_RATE as usize;
+// Once the live window grows past this, its text is committed and a new window starts
+const MAX_WINDOW_SAMPLES: usize = 20 * SAMPLE_RATE as usize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptEvent {
+    pub session_id: String,
+    pub text: String,
+    /// Detected language; only known for the final transcript.
+    pub language: Option<String>,
+}
+
+struct Session {
+    resampler: StreamResampler,
+    // 16kHz mono audio received so far
+    audio: Vec<f32>,
+    // Start of the window that partials are decoded over
+    window_start: usize,
+    // Text of windows that have already slid out
+    committed: String,
+    // Latest partial text for the current window
+    live: String,
+    // Audio length at the last partial decode
+    last_decoded_len: usize,
+    decoding: bool,
+    finished: bool,
+}
+
+/// Live transcription sessions fed with audio chunks while the user talks.
+/// Partial results are emitted as `transcript-partial` events from a sliding
+/// window; the final pass runs over the whole utterance.
+pub struct StreamingAsr {
+    engine: Arc<AsrEngine>,
+    sessions: Mutex<HashMap<String, Arc<Mutex<Session>>>>,
+}
+
+impl StreamingAsr {
+    pub fn new(engine: Arc<AsrEngine>) -> Self {
+        Self {
+            engine,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn start(&self, sample_rate: u32) -> Result<String, AppError> {
+        let session = Session {
+            resampler: StreamResampler::new(sample_rate, SAMPLE_RATE)?,
+            audio: Vec::new(),
+            window_start: 0,
+            committed: String::new(),
+            live: String::new(),
+            last_decoded_len: 0,
+            decoding: false,
+            finished: false,
+        };
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        self.sessions.lock()
+            .map_err(|_| AppError::Internal("Streaming session lock poisoned".into()))?
+            .insert(session_id.clone(), Arc::new(Mutex::new(session)));
+
+        println!("Started transcript stream {} at {}Hz", session_id, sample_rate);
+        Ok(session_id)
+    }
+
+    /// Appends mono samples at the session's input rate and schedules a
+    /// partial decode if enough new audio has accumulated.
+    pub fn push(&self, session_id: &str, samples: &[f32], app_handle: &tauri::AppHandle) -> Result<(), AppError> {
+        let session = self.session(session_id)?;
+
+        let window = {
+            let mut s = session.lock().map_err(|_| AppError::Internal("Streaming session lock poisoned".into()))?;
+            let resampled = s.resampler.push(samples)?;
+            s.audio.extend_from_slice(&resampled);
+
+            if s.decoding || s.audio.len() - s.last_decoded_len < PARTIAL_STEP_SAMPLES {
+                return Ok(());
+            }
+
+            if s.audio.len() - s.window_start > MAX_WINDOW_SAMPLES {
+                // Freeze the text of the current window and slide past the audio it covered
+                let live = std::mem::take(&mut s.live);
+                s.committed = join_text(&s.committed, &live);
+                s.window_start = s.last_decoded_len;
+            }
+
+            if s.audio.len() - s.window_start < MIN_WINDOW_SAMPLES {
+                return Ok(());
+            }
+
+            s.decoding = true;
+            s.last_decoded_len = s.audio.len();
+            s.audio[s.window_start..].to_vec()
+        };
+
+        let engine = self.engine.clone();
+        let app_handle = app_handle.clone();
+        let session_id = session_id.to_string();
+        tauri::async_runtime::spawn_blocking(move || {
+            // Decoding only the voiced part keeps Whisper from inventing words in pauses
+            let result = match vad::trim_silence(&window, SAMPLE_RATE, &vad::VadConfig::default()) {
+                Some(speech) => engine.decode(speech, &app_handle),
+                None => Ok(String::new()),
+            };
+
+            let mut s = match session.lock() {
+                Ok(s) => s,
+                Err(_) => return,
+            };
+            s.decoding = false;
+
+            if s.finished {
+                return;
+            }
+
+            match result {
+                Ok(text) => {
+                    s.live = text;
+                    let text = join_text(&s.committed, &s.live);
+                    if let Err(e) = app_handle.emit("transcript-partial", TranscriptEvent { session_id, text, language: None }) {
+                        eprintln!("Failed to emit partial transcript: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Partial transcription failed: {}", e),
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Flushes the session, transcribes the complete utterance and emits
+    /// `transcript-final`.
+    pub async fn finish(&self, session_id: &str, context: AsrContext, app_handle: &tauri::AppHandle) -> Result<Utterance, AppError> {
+        let session = self.remove(session_id)?;
+
+        let audio = {
+            let mut s = session.lock().map_err(|_| AppError::Internal("Streaming session lock poisoned".into()))?;
+            s.finished = true;
+            let tail = s.resampler.flush()?;
+            s.audio.extend_from_slice(&tail);
+            std::mem::take(&mut s.audio)
+        };
+
+        println!("Finishing transcript stream {} with {} samples", session_id, audio.len());
+
+        if audio.len() < SAMPLE_RATE as usize / 10 {
+            return Err(AppError::NoSpeech("Audio is too short (less than 0.1 seconds)".into()));
+        }
+
+        let engine = self.engine.clone();
+        let handle = app_handle.clone();
+        let utterance = tokio::task::spawn_blocking(move || {
+            engine.transcribe(&audio, &context, &handle).map(|transcript| Utterance { transcript, samples: audio })
+        })
+        .await
+        .context(AppError::Internal, "Task failed")??;
+
+        app_handle
+            .emit("transcript-final", TranscriptEvent {
+                session_id: session_id.to_string(),
+                text: utterance.transcript.text.clone(),
+                language: Some(utterance.transcript.language.clone()),
+            })
+            .context(AppError::Internal, "Failed to emit final transcript")?;
+
+        Ok(utterance)
+    }
+
+    pub fn cancel(&self, session_id: &str) -> Result<(), AppError> {
+        let session = self.remove(session_id)?;
+        if let Ok(mut s) = session.lock() {
+            s.finished = true;
+        }
+        Ok(())
+    }
+
+    fn session(&self, session_id: &str) -> Result<Arc<Mutex<Session>>, AppError> {
+        self.sessions.lock()
+            .map_err(|_| AppError::Internal("Streaming session lock poisoned".into()))?
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| AppError::InvalidRequest(format!("Unknown transcript stream: {}", session_id).into()))
+    }
+
+    fn remove(&self, session_id: &str) -> Result<Arc<Mutex<Session>>, AppError> {
+        self.sessions.lock()
+            .map_err(|_| AppError::Internal("Streaming session lock poisoned".into()))?
+            .remove(session_id)
+            .ok_or_else(|| AppError::InvalidRequest(format!("Unknown transcript stream: {}", session_id).into()))
+    }
+}
+
+fn join_text(committed: &str, live: &str) -> String {
+    match (committed.is_empty(), live.is_empty()) {
+        (true, _) => live.to_string(),
+        (false, true) => committed.to_string(),
+        (false, false) => format!("{} {}", committed, live),
+    }
+}
@@ -0,0 +1,120 @@
+use crate::db::{Database, Message};
+use crate::error::AppError;
+use crate::ollama::{self, ChatMessage};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+const TITLE_PROMPT: &str = "Write a title of at most six words for the conversation below. \
+Reply with the title only, without quotes or a full stop.";
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation below in three or four sentences. \
+Keep names, facts and decisions that matter later. Reply with the summary only.";
+
+// Conversations shorter than this don't need a summary
+const SUMMARY_MIN_MESSAGES: usize = 12;
+// Refresh the summary after this many new messages
+const SUMMARY_REFRESH_MESSAGES: usize = 8;
+// Keeps background requests small; the oldest new messages are dropped first
+const MAX_TRANSCRIPT_CHARS: usize = 12_000;
+const MAX_TITLE_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationUpdatedEvent {
+    pub conversation_id: String,
+}
+
+/// Titles a conversation after its first exchange and keeps a summary of
+/// long ones, then emits `conversation-updated`. Meant to run in the
+/// background after each reply; failures are only logged.
+pub async fn refresh(conversation_id: String, app_handle: tauri::AppHandle) {
+    match update(&conversation_id, &app_handle).await {
+        Ok(false) => {}
+        Ok(true) => {
+            let event = ConversationUpdatedEvent { conversation_id };
+            if let Err(e) = app_handle.emit("conversation-updated", event) {
+                eprintln!("Failed to emit conversation update: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to update title or summary of {}: {}", conversation_id, e),
+    }
+}
+
+// Returns whether anything changed
+async fn update(conversation_id: &str, app_handle: &tauri::AppHandle) -> Result<bool, AppError> {
+    let db = app_handle.state::<Database>();
+    let conversation = db.get_conversation(conversation_id)?;
+    let messages = db.get_messages(conversation_id)?;
+    let mut changed = false;
+
+    if conversation.title.is_none() && messages.len() >= 2 {
+        let reply = ollama::chat(vec![
+            ChatMessage::new("system", TITLE_PROMPT),
+            ChatMessage::new("user", transcript(&messages[..2], None)),
+        ])
+        .await?;
+
+        if let Some(title) = clean_title(&reply) {
+            println!("Titled conversation {}: {}", conversation_id, title);
+            db.set_generated_title(conversation_id, &title)?;
+            changed = true;
+        }
+    }
+
+    let unsummarized = messages.len().saturating_sub(conversation.summarized_messages);
+    if messages.len() >= SUMMARY_MIN_MESSAGES && unsummarized >= SUMMARY_REFRESH_MESSAGES {
+        let new_messages = &messages[conversation.summarized_messages.min(messages.len())..];
+        let reply = ollama::chat(vec![
+            ChatMessage::new("system", SUMMARY_PROMPT),
+            ChatMessage::new("user", transcript(new_messages, conversation.summary.as_deref())),
+        ])
+        .await?;
+
+        let summary = reply.trim();
+        if !summary.is_empty() {
+            println!("Summarized {} messages of conversation {}", messages.len(), conversation_id);
+            db.set_summary(conversation_id, summary, messages.len())?;
+            changed = true;
+        }
+    }
+
+    Ok(changed)
+}
+
+// Formats messages as a plain transcript, optionally after an earlier summary
+fn transcript(messages: &[Message], summary: Option<&str>) -> String {
+    let mut lines: Vec<String> = messages
+        .iter()
+        .map(|message| {
+            // Interrupted replies only count for what the user actually heard
+            let speaker = if message.role == "user" { "User" } else { "Assistant" };
+            format!("{}: {}", speaker, message.heard_text())
+        })
+        .collect();
+
+    let mut length: usize = lines.iter().map(|line| line.len() + 1).sum();
+    while length > MAX_TRANSCRIPT_CHARS && lines.len() > 1 {
+        length -= lines.remove(0).len() + 1;
+    }
+
+    match summary {
+        Some(summary) => format!("Summary so far: {}\n\nNew messages:\n{}", summary, lines.join("\n")),
+        None => lines.join("\n"),
+    }
+}
+
+// Models like to wrap titles in quotes, prefix them or add a trailing full stop
+fn clean_title(reply: &str) -> Option<String> {
+    let line = reply.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+    let title = line
+        .trim()
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '*' | '#' | '“' | '”'))
+        .trim_end_matches('.')
+        .trim();
+
+    if title.is_empty() {
+        return None;
+    }
+
+    Some(title.chars().take(MAX_TITLE_CHARS).collect())
+}
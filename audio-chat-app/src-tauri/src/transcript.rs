@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+/// Segments whose mean token probability is below this are flagged
+/// `low_confidence`. They are kept: accented and mixed-language speech
+/// often scores this low while being transcribed correctly.
+const LOW_SEGMENT_CONFIDENCE: f32 = 0.4;
+
+/// Phrases Whisper tends to invent on silence and noise, learned from the
+/// subtitles it was trained on. Only dropped when Whisper isn't sure of them.
+const SUSPECT_PHRASES: &[&str] = &[
+    "thank you for watching",
+    "thanks for watching",
+    "please subscribe",
+    "like and subscribe",
+    "subtitles by",
+    "amara.org",
+];
+const SUSPECT_PHRASE_CONFIDENCE: f32 = 0.75;
+
+/// Structured Whisper output for one utterance. Times are milliseconds from
+/// the start of the recording that was transcribed.
+#[derive(Debug, Clone, Serialize)]
+pub struct Transcript {
+    pub text: String,
+    /// ISO 639-1 code of the spoken language, e.g. "de". English-only
+    /// models always report "en".
+    pub language: String,
+    /// Id of the Whisper model that produced the text.
+    pub model: String,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+impl Transcript {
+    /// Mean token probability (0..1) of each segment.
+    pub fn segment_confidences(&self) -> Vec<f32> {
+        self.segments.iter().map(|segment| segment.confidence).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub text: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Mean probability of the segment's tokens.
+    pub confidence: f32,
+    /// Whisper was unsure of the whole segment; shown highlighted.
+    #[serde(default)]
+    pub low_confidence: bool,
+    pub words: Vec<TranscriptWord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptWord {
+    pub text: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Probability of the word's least certain token.
+    pub confidence: f32,
+    pub tokens: Vec<TokenConfidence>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenConfidence {
+    pub text: String,
+    pub probability: f32,
+}
+
+/// A text token as Whisper produced it, with times already relative to the
+/// recording.
+pub struct RawToken {
+    /// Tokens can end in the middle of a UTF-8 character, so they are kept
+    /// as bytes until whole words are put together.
+    pub bytes: Vec<u8>,
+    pub probability: f32,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+impl TranscriptSegment {
+    /// Builds a segment from its text tokens. A token starting with a space
+    /// starts a new word.
+    pub fn from_tokens(text: String, start_ms: u64, end_ms: u64, tokens: &[RawToken]) -> Self {
+        let mut words: Vec<(Vec<u8>, Vec<&RawToken>)> = Vec::new();
+        for token in tokens {
+            match words.last_mut() {
+                Some((bytes, word_tokens)) if !token.bytes.starts_with(b" ") => {
+                    bytes.extend_from_slice(&token.bytes);
+                    word_tokens.push(token);
+                }
+                _ => words.push((token.bytes.clone(), vec![token])),
+            }
+        }
+
+        let words = words
+            .into_iter()
+            .filter_map(|(bytes, tokens)| {
+                let text = String::from_utf8_lossy(&bytes).trim().to_string();
+                if text.is_empty() {
+                    return None;
+                }
+                Some(TranscriptWord {
+                    text,
+                    start_ms: tokens.first()?.start_ms,
+                    end_ms: tokens.last()?.end_ms,
+                    confidence: tokens.iter().map(|token| token.probability).fold(1.0, f32::min),
+                    tokens: tokens
+                        .iter()
+                        .map(|token| TokenConfidence {
+                            text: String::from_utf8_lossy(&token.bytes).to_string(),
+                            probability: token.probability,
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+
+        let confidence = if tokens.is_empty() {
+            0.0
+        } else {
+            tokens.iter().map(|token| token.probability).sum::<f32>() / tokens.len() as f32
+        };
+
+        Self {
+            text: text.trim().to_string(),
+            start_ms,
+            end_ms,
+            confidence,
+            low_confidence: confidence < LOW_SEGMENT_CONFIDENCE,
+            words,
+        }
+    }
+}
+
+/// Why `segment` looks like something Whisper made up rather than heard, if
+/// it does. `previous` is the segment kept before it. Low confidence alone
+/// isn't enough; see `LOW_SEGMENT_CONFIDENCE`.
+pub fn hallucination(segment: &TranscriptSegment, previous: Option<&TranscriptSegment>) -> Option<&'static str> {
+    let text = segment.text.as_str();
+    let annotation = [('[', ']'), ('(', ')'), ('*', '*'), ('♪', '♪')]
+        .iter()
+        .any(|&(open, close)| text.len() > 1 && text.starts_with(open) && text.ends_with(close));
+    if annotation {
+        return Some("non-speech annotation");
+    }
+
+    let lower = text.to_lowercase();
+    if segment.confidence < SUSPECT_PHRASE_CONFIDENCE && SUSPECT_PHRASES.iter().any(|phrase| lower.contains(phrase)) {
+        return Some("common hallucination");
+    }
+
+    // Whisper sometimes loops, emitting the same line over and over
+    if previous.is_some_and(|previous| previous.text.eq_ignore_ascii_case(text)) {
+        return Some("repeated segment");
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, confidence: f32) -> TranscriptSegment {
+        TranscriptSegment {
+            text: text.to_string(),
+            start_ms: 0,
+            end_ms: 1000,
+            confidence,
+            low_confidence: confidence < LOW_SEGMENT_CONFIDENCE,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn flags_annotations() {
+        for text in ["[MUSIC]", "(applause)", "*laughs*", "♪"] {
+            assert_eq!(hallucination(&segment(text, 0.9), None), Some("non-speech annotation"), "{}", text);
+        }
+        assert_eq!(hallucination(&segment("[", 0.9), None), None);
+        assert_eq!(hallucination(&segment("(well) maybe", 0.9), None), None);
+    }
+
+    #[test]
+    fn flags_suspect_phrases_only_when_unsure() {
+        let phrase = "Thanks for watching!";
+        assert_eq!(hallucination(&segment(phrase, 0.5), None), Some("common hallucination"));
+        assert_eq!(hallucination(&segment(phrase, SUSPECT_PHRASE_CONFIDENCE - 0.01), None), Some("common hallucination"));
+        assert_eq!(hallucination(&segment(phrase, SUSPECT_PHRASE_CONFIDENCE), None), None);
+        // Low confidence alone is fine
+        assert_eq!(hallucination(&segment("Wie spät ist es?", 0.2), None), None);
+    }
+
+    #[test]
+    fn flags_repeated_segments() {
+        let previous = segment("Turn left here.", 0.9);
+        assert_eq!(hallucination(&segment("turn left here.", 0.9), Some(&previous)), Some("repeated segment"));
+        assert_eq!(hallucination(&segment("Then right.", 0.9), Some(&previous)), None);
+        assert_eq!(hallucination(&segment("Turn left here.", 0.9), None), None);
+    }
+}
@@ -1,41 +1,450 @@
+use pyo3::prelude::*;
+use pyo3::types::IntoPyDict;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, RwLock};
 use tauri::Manager;
 
-pub async fn synthesize_speech(text: String, app_handle: tauri::AppHandle) -> Result<String, String> {
-    // For now, use macOS built-in TTS as a fallback while MeloTTS is being set up
-    // This ensures the pipeline works end-to-end
-    
-    // Use app data directory instead of temp directory for better access control
-    let app_data_dir = app_handle.path().app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-    
-    // Ensure the directory exists
-    std::fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
-    
-    let output_path = app_data_dir.join(format!("tts_output_{}.wav", uuid::Uuid::new_v4()));
-    
-    // Use macOS `say` command to generate speech
-    let output = std::process::Command::new("say")
-        .arg("-o")
-        .arg(&output_path)
-        .arg("--file-format=WAVE")
-        .arg("--data-format=LEI16")
-        .arg(&text)
-        .output()
-        .map_err(|e| format!("Failed to execute say command: {}", e))?;
-    
+use crate::error::{AppError, Context};
+use crate::settings::Settings;
+
+/// Which synthesizer to use. `Auto` picks `say` on macOS, Piper when a voice
+/// model is configured, and espeak-ng otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TtsBackend {
+    #[default]
+    Auto,
+    Say,
+    Melo,
+    Piper,
+    Espeak,
+}
+
+pub trait TtsEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Writes speech for `text`, spoken in `language` (ISO 639-1) if known,
+    /// to `output` as a WAV file. Blocking.
+    fn synthesize(&self, text: &str, language: Option<&str>, output: &Path) -> Result<(), AppError>;
+}
+
+/// The configured voice, with overrides for replies in other languages.
+#[derive(Debug, Clone, Default)]
+pub struct Voices {
+    default: Option<String>,
+    languages: HashMap<String, String>,
+}
+
+impl Voices {
+    fn from_settings(settings: &Settings) -> Self {
+        Self {
+            default: settings.tts_voice.clone(),
+            languages: settings.tts_language_voices.clone(),
+        }
+    }
+
+    /// The voice set for `language`, if any.
+    fn for_language(&self, language: Option<&str>) -> Option<&str> {
+        language.and_then(|language| self.languages.get(language)).map(String::as_str)
+    }
+}
+
+// English needs no special voice; for anything else engines look further
+fn non_english(language: Option<&str>) -> Option<&str> {
+    language.filter(|language| *language != "en")
+}
+
+/// macOS built-in speech.
+pub struct SayEngine {
+    voices: Voices,
+    /// Installed voice found for each language, looked up once.
+    found: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl SayEngine {
+    fn new(voices: Voices) -> Self {
+        Self { voices, found: Mutex::new(HashMap::new()) }
+    }
+
+    // First installed voice for `language`, from `say -v ?` lines like
+    // "Anna                de_DE    # Hallo! Ich heiße Anna."
+    fn find_voice(&self, language: &str) -> Option<String> {
+        let mut found = match self.found.lock() {
+            Ok(found) => found,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        found
+            .entry(language.to_string())
+            .or_insert_with(|| {
+                let output = Command::new("say").arg("-v").arg("?").output().ok()?;
+                let prefix = format!("{}_", language);
+                String::from_utf8_lossy(&output.stdout).lines().find_map(|line| {
+                    let (voice, rest) = line.split_once("  ")?;
+                    rest.trim_start().starts_with(&prefix).then(|| voice.trim().to_string())
+                })
+            })
+            .clone()
+    }
+}
+
+impl TtsEngine for SayEngine {
+    fn name(&self) -> &'static str {
+        "say"
+    }
+
+    fn synthesize(&self, text: &str, language: Option<&str>, output: &Path) -> Result<(), AppError> {
+        let voice = match (self.voices.for_language(language), non_english(language)) {
+            (Some(voice), _) => Some(voice.to_string()),
+            (None, Some(language)) => self.find_voice(language).or_else(|| self.voices.default.clone()),
+            (None, None) => self.voices.default.clone(),
+        };
+
+        let mut command = Command::new("say");
+        command
+            .arg("-o")
+            .arg(output)
+            .arg("--file-format=WAVE")
+            .arg("--data-format=LEI16");
+        if let Some(voice) = &voice {
+            command.arg("-v").arg(voice);
+        }
+        command.arg(text);
+
+        run(command, None)
+    }
+}
+
+/// espeak-ng, available from every Linux distribution's package manager.
+pub struct EspeakEngine {
+    voices: Voices,
+}
+
+impl TtsEngine for EspeakEngine {
+    fn name(&self) -> &'static str {
+        "espeak-ng"
+    }
+
+    fn synthesize(&self, text: &str, language: Option<&str>, output: &Path) -> Result<(), AppError> {
+        // espeak-ng names its voices after language codes, so "de" works as is
+        let voice = self.voices.for_language(language)
+            .or(non_english(language))
+            .or(self.voices.default.as_deref());
+
+        let mut command = Command::new("espeak-ng");
+        command.arg("-w").arg(output).arg("--stdin");
+        if let Some(voice) = voice {
+            command.arg("-v").arg(voice);
+        }
+
+        run(command, Some(text))
+    }
+}
+
+/// Piper neural TTS running an ONNX voice model locally. Each language
+/// needs its own model; per-language voices are model paths.
+pub struct PiperEngine {
+    model: PathBuf,
+    voices: Voices,
+}
+
+impl TtsEngine for PiperEngine {
+    fn name(&self) -> &'static str {
+        "piper"
+    }
+
+    fn synthesize(&self, text: &str, language: Option<&str>, output: &Path) -> Result<(), AppError> {
+        let model = self.voices.for_language(language).map(Path::new).unwrap_or(&self.model);
+
+        let mut command = Command::new("piper");
+        command
+            .arg("--model")
+            .arg(model)
+            .arg("--output_file")
+            .arg(output);
+
+        run(command, Some(text))
+    }
+}
+
+/// MeloTTS through the embedded Python interpreter. Each language has its
+/// own model, which takes several seconds to load, so models are created on
+/// first use and kept around.
+pub struct MeloEngine {
+    voices: Voices,
+    models: Mutex<HashMap<&'static str, Py<PyAny>>>,
+}
+
+// MeloTTS model languages by ISO 639-1 code; anything else is spoken in English
+const MELO_LANGUAGES: &[(&str, &str)] = &[("en", "EN"), ("es", "ES"), ("fr", "FR"), ("zh", "ZH"), ("ja", "JP"), ("ko", "KR")];
+
+impl MeloEngine {
+    fn model_language(language: Option<&str>) -> &'static str {
+        language
+            .and_then(|language| MELO_LANGUAGES.iter().find(|(code, _)| *code == language))
+            .map(|(_, model_language)| *model_language)
+            .unwrap_or("EN")
+    }
+
+    // The speaker to use from `model_language`'s model. Models other than
+    // English have a single speaker named after the language.
+    fn speaker<'a>(&'a self, language: Option<&str>, model_language: &'a str) -> &'a str {
+        if let Some(voice) = self.voices.for_language(language) {
+            return voice;
+        }
+        match model_language {
+            "EN" => self.voices.default.as_deref().unwrap_or("EN-US"),
+            other => other,
+        }
+    }
+
+    fn load_model(py: Python<'_>, language: &str) -> PyResult<Py<PyAny>> {
+        println!("Loading MeloTTS {} model...", language);
+        let api = py.import_bound("melo.api")?;
+        let kwargs = [("language", language), ("device", "auto")].into_py_dict_bound(py);
+        let model = api.getattr("TTS")?.call((), Some(&kwargs))?;
+        println!("MeloTTS {} model loaded", language);
+        Ok(model.unbind())
+    }
+}
+
+impl TtsEngine for MeloEngine {
+    fn name(&self) -> &'static str {
+        "melotts"
+    }
+
+    fn synthesize(&self, text: &str, language: Option<&str>, output: &Path) -> Result<(), AppError> {
+        let model_language = Self::model_language(language);
+        let speaker = self.speaker(language, model_language);
+        let mut models = self.models.lock()
+            .map_err(|_| AppError::Internal("MeloTTS lock poisoned".into()))?;
+
+        Python::with_gil(|py| -> PyResult<()> {
+            let model = match models.entry(model_language) {
+                Entry::Occupied(entry) => entry.get().bind(py).clone(),
+                Entry::Vacant(entry) => entry.insert(Self::load_model(py, model_language)?).bind(py).clone(),
+            };
+
+            let speaker_id = model
+                .getattr("hps")?
+                .getattr("data")?
+                .getattr("spk2id")?
+                .get_item(speaker)?;
+            model.call_method1("tts_to_file", (text, speaker_id, output.to_string_lossy().as_ref()))?;
+            Ok(())
+        })
+        .context(
+            AppError::Synthesis,
+            format!(
+                "MeloTTS failed to speak {} as '{}'. Install it with 'pip install git+https://github.com/myshell-ai/MeloTTS.git'",
+                model_language, speaker
+            ),
+        )
+    }
+}
+
+fn run(mut command: Command, stdin: Option<&str>) -> Result<(), AppError> {
+    let program = command.get_program().to_string_lossy().to_string();
+
+    let mut child = command
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(AppError::Synthesis, format!("Failed to execute {}", program))?;
+
+    if let (Some(text), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(text.as_bytes())
+            .context(AppError::Synthesis, format!("Failed to send text to {}", program))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .context(AppError::Synthesis, format!("Failed to wait for {}", program))?;
+
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("TTS failed: {}", stderr));
+        return Err(AppError::Synthesis(format!("TTS failed ({}): {}", program, stderr).into()));
     }
-    
+
+    Ok(())
+}
+
+/// Builds the engine selected in `settings`.
+pub fn create_engine(settings: &Settings) -> Result<Arc<dyn TtsEngine>, AppError> {
+    let voices = Voices::from_settings(settings);
+
+    let backend = match settings.tts_engine {
+        TtsBackend::Auto if cfg!(target_os = "macos") => TtsBackend::Say,
+        TtsBackend::Auto if settings.piper_model.is_some() => TtsBackend::Piper,
+        TtsBackend::Auto => TtsBackend::Espeak,
+        backend => backend,
+    };
+
+    let engine: Arc<dyn TtsEngine> = match backend {
+        TtsBackend::Say => Arc::new(SayEngine::new(voices)),
+        TtsBackend::Espeak => Arc::new(EspeakEngine { voices }),
+        TtsBackend::Piper => {
+            let model = settings.piper_model.as_ref()
+                .ok_or_else(|| AppError::InvalidRequest("Piper needs a voice model; set piper_model in settings".into()))?;
+            Arc::new(PiperEngine { model: PathBuf::from(model), voices })
+        }
+        TtsBackend::Melo => Arc::new(MeloEngine {
+            voices,
+            models: Mutex::new(HashMap::new()),
+        }),
+        TtsBackend::Auto => unreachable!("auto is resolved above"),
+    };
+
+    Ok(engine)
+}
+
+/// The active TTS engine, swappable at runtime when the settings change.
+pub struct Tts {
+    engine: RwLock<Arc<dyn TtsEngine>>,
+}
+
+impl Tts {
+    pub fn new(settings: &Settings) -> Self {
+        let engine = create_engine(settings).unwrap_or_else(|e| {
+            eprintln!("{}; falling back to the platform default", e);
+            create_engine(&Settings::default()).expect("default TTS engine needs no configuration")
+        });
+        println!("Using {} for text-to-speech", engine.name());
+
+        Self { engine: RwLock::new(engine) }
+    }
+
+    pub fn engine(&self) -> Arc<dyn TtsEngine> {
+        match self.engine.read() {
+            Ok(engine) => engine.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Switches to the engine described by `settings`.
+    pub fn configure(&self, settings: &Settings) -> Result<(), AppError> {
+        let engine = create_engine(settings)?;
+        println!("Switched text-to-speech to {}", engine.name());
+
+        let mut current = self.engine.write()
+            .map_err(|_| AppError::Internal("TTS engine lock poisoned".into()))?;
+        *current = engine;
+        Ok(())
+    }
+}
+
+/// Where synthesized clips go until they have been played. Kept in the app
+/// data directory instead of the temp directory for better access control.
+fn clip_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let app_data_dir = app_handle.path().app_data_dir()
+        .context(AppError::Internal, "Failed to get app data directory")?;
+    Ok(app_data_dir.join("speech"))
+}
+
+/// Deletes clips that have been played and, for voice turns, copied into the
+/// audio store.
+pub fn remove_clips(paths: &[String]) {
+    for path in paths {
+        if let Err(e) = std::fs::remove_file(path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to remove speech clip {}: {}", path, e);
+            }
+        }
+    }
+}
+
+/// Deletes clips left behind by earlier runs, including those older versions
+/// wrote straight into the app data directory. Nothing can be playing them
+/// yet, so call this on startup.
+pub fn remove_leftover_clips(app_handle: &tauri::AppHandle) -> Result<(), AppError> {
+    let dir = clip_dir(app_handle)?;
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .context(AppError::Internal, format!("Failed to remove {}", dir.display()))?;
+    }
+
+    let app_data_dir = dir.parent().unwrap_or(&dir);
+    let Ok(entries) = std::fs::read_dir(app_data_dir) else {
+        return Ok(());
+    };
+    let legacy: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("tts_output_") && name.ends_with(".wav"))
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    remove_clips(&legacy);
+    Ok(())
+}
+
+pub async fn synthesize_speech(
+    engine: Arc<dyn TtsEngine>,
+    text: String,
+    language: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let dir = clip_dir(&app_handle)?;
+    std::fs::create_dir_all(&dir)
+        .context(AppError::Internal, format!("Failed to create {}", dir.display()))?;
+
+    let output_path = dir.join(format!("tts_output_{}.wav", uuid::Uuid::new_v4()));
+
+    // Engines block on a subprocess or Python, so keep them off the async runtime
+    let path = output_path.clone();
+    tokio::task::spawn_blocking(move || engine.synthesize(&text, language.as_deref(), &path))
+        .await
+        .context(AppError::Internal, "Task failed")??;
+
     // Check if output file was created
     if !output_path.exists() {
-        return Err("TTS did not generate output file".to_string());
+        return Err(AppError::Synthesis("TTS did not generate output file".into()));
     }
-    
+
     println!("TTS file generated successfully at: {}", output_path.display());
     println!("File size: {} bytes", std::fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0));
-    
+
     Ok(output_path.to_string_lossy().to_string())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn melo(default: Option<&str>, languages: &[(&str, &str)]) -> MeloEngine {
+        let voices = Voices {
+            default: default.map(str::to_string),
+            languages: languages.iter().map(|(language, voice)| (language.to_string(), voice.to_string())).collect(),
+        };
+        MeloEngine { voices, models: Mutex::new(HashMap::new()) }
+    }
+
+    #[test]
+    fn melo_picks_model_and_speaker_per_language() {
+        let engine = melo(Some("EN-BR"), &[("fr", "FR"), ("de", "EN-AU")]);
+
+        let speak = |language| {
+            let model_language = MeloEngine::model_language(language);
+            (model_language, engine.speaker(language, model_language).to_string())
+        };
+        assert_eq!(speak(Some("en")), ("EN", "EN-BR".to_string()));
+        assert_eq!(speak(None), ("EN", "EN-BR".to_string()));
+        assert_eq!(speak(Some("fr")), ("FR", "FR".to_string()));
+        assert_eq!(speak(Some("ja")), ("JP", "JP".to_string()));
+        assert_eq!(speak(Some("es")), ("ES", "ES".to_string()));
+        // No German model; the configured English speaker reads it
+        assert_eq!(speak(Some("de")), ("EN", "EN-AU".to_string()));
+
+        let engine = melo(None, &[]);
+        assert_eq!(engine.speaker(Some("en"), "EN"), "EN-US");
+    }
+}
@@ -0,0 +1,447 @@
+use crate::asr::{self, AsrEngine, Utterance};
+use crate::audio_processing;
+use crate::audio_store::AudioStore;
+use crate::chat::{self, Reply};
+use crate::db::{Database, MessageMetadata};
+use crate::error::{AppError, Context};
+use crate::recorder::Recorder;
+use crate::settings::Settings;
+use crate::speech_pipeline::{SpeechQueue, SpokenSegment};
+use crate::streaming_asr::StreamingAsr;
+use crate::tts::{self, TtsEngine};
+use crate::vocabulary::AsrContext;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{Emitter, Manager};
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Transcribing,
+    Thinking,
+    Speaking,
+    Done,
+    Cancelled,
+    /// The turn ended with an error; `voice_turn` returns it.
+    Failed,
+}
+
+/// Milliseconds spent in each stage of a turn.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TurnTimings {
+    pub transcribing_ms: u64,
+    pub thinking_ms: u64,
+    pub speaking_ms: u64,
+    pub total_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageEvent {
+    pub turn_id: String,
+    pub stage: Stage,
+    pub elapsed_ms: u64,
+    /// Set once the turn has ended.
+    pub timings: Option<TurnTimings>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnResult {
+    pub turn_id: String,
+    pub transcript: String,
+    /// Language the user spoke, which the reply is given in too.
+    pub language: String,
+    pub response: String,
+    pub cancelled: bool,
+    pub timings: TurnTimings,
+}
+
+/// Where the user's speech comes from.
+pub enum TurnAudio {
+    /// The native recording that is currently running.
+    Recording,
+    /// An encoded recording uploaded from the webview.
+    Blob(Vec<u8>),
+}
+
+/// Backend services a turn runs through.
+pub struct TurnServices {
+    pub asr: Arc<AsrEngine>,
+    pub transcripts: Arc<StreamingAsr>,
+    pub recorder: Arc<Recorder>,
+    pub tts: Arc<dyn TtsEngine>,
+    pub settings: Settings,
+    pub turns: Arc<Turns>,
+}
+
+/// What the user heard of a turn's reply. Filled in from both ends: the turn
+/// stores the reply's message id once it is saved, and a barge-in stores how
+/// many speech segments had started playing. Whichever arrives second writes
+/// the spoken text to the database.
+pub struct TurnRecord {
+    segments: Arc<Mutex<Vec<SpokenSegment>>>,
+    message_id: Option<String>,
+    played_segments: Option<usize>,
+}
+
+impl TurnRecord {
+    fn spoken(&self, played: usize) -> String {
+        let segments = match self.segments.lock() {
+            Ok(segments) => segments,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        segments.iter().take(played).map(|segment| segment.text.as_str()).collect::<Vec<_>>().join(" ")
+    }
+
+    /// The reply was saved; returns the interruption to record, if reported already.
+    fn saved(&mut self, message_id: String) -> Option<(String, String)> {
+        self.message_id = Some(message_id.clone());
+        self.played_segments.map(|played| (message_id, self.spoken(played)))
+    }
+
+    /// The user barged in; returns the interruption to record, if the reply was saved already.
+    fn interrupted(&mut self, played: usize) -> Option<(String, String)> {
+        self.played_segments = Some(played);
+        self.message_id.clone().map(|message_id| (message_id, self.spoken(played)))
+    }
+}
+
+/// The most recent turn. Only the latest reply can be playing, so older turns
+/// are forgotten as soon as a new one starts.
+#[derive(Default)]
+pub struct Turns {
+    latest: Mutex<Option<(String, TurnRecord)>>,
+    // Speech clips of earlier turns that are done with them. The webview may
+    // still be playing them, so they are deleted when the next turn starts.
+    spent_clips: Mutex<Vec<String>>,
+}
+
+impl Turns {
+    fn begin(&self, turn_id: &str, segments: Arc<Mutex<Vec<SpokenSegment>>>) -> Result<(), AppError> {
+        let mut latest = self.latest.lock()
+            .map_err(|_| AppError::Internal("Turns lock poisoned".into()))?;
+        *latest = Some((turn_id.to_string(), TurnRecord { segments, message_id: None, played_segments: None }));
+        drop(latest);
+
+        let spent = match self.spent_clips.lock() {
+            Ok(mut spent) => std::mem::take(&mut *spent),
+            Err(_) => Vec::new(),
+        };
+        tts::remove_clips(&spent);
+        Ok(())
+    }
+
+    /// Hands over speech clips that have been copied into the audio store, or
+    /// that never will be, for deletion once the next turn starts.
+    pub fn spent(&self, clips: Vec<String>) {
+        if let Ok(mut spent) = self.spent_clips.lock() {
+            spent.extend(clips);
+        }
+    }
+
+    fn update(
+        &self,
+        turn_id: &str,
+        f: impl FnOnce(&mut TurnRecord) -> Option<(String, String)>,
+    ) -> Result<Option<(String, String)>, AppError> {
+        let mut latest = self.latest.lock()
+            .map_err(|_| AppError::Internal("Turns lock poisoned".into()))?;
+        Ok(match latest.as_mut() {
+            Some((id, record)) if id == turn_id => f(record),
+            _ => None,
+        })
+    }
+}
+
+/// Barge-in: records that playback of `turn_id`'s reply stopped after
+/// `played_segments` segments had started. The caller cancels the turn.
+pub async fn interrupt(
+    turns: &Turns,
+    turn_id: &str,
+    played_segments: usize,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    println!("Turn {} interrupted after {} spoken segments", turn_id, played_segments);
+    if let Some((message_id, spoken)) = turns.update(turn_id, |record| record.interrupted(played_segments))? {
+        app_handle.state::<Database>().record_interruption(&message_id, Some(&spoken))?;
+    }
+    Ok(())
+}
+
+// Emits `turn-stage` events and keeps track of how long each stage took
+struct StageClock {
+    turn_id: String,
+    app_handle: tauri::AppHandle,
+    started: Instant,
+    stage_started: Instant,
+    timings: TurnTimings,
+}
+
+impl StageClock {
+    fn start(turn_id: &str, app_handle: &tauri::AppHandle) -> Self {
+        let now = Instant::now();
+        Self {
+            turn_id: turn_id.to_string(),
+            app_handle: app_handle.clone(),
+            started: now,
+            stage_started: now,
+            timings: TurnTimings::default(),
+        }
+    }
+
+    /// Moves to `stage`, charging the time since the last call to `previous`.
+    fn enter(&mut self, previous: Option<Stage>, stage: Stage) {
+        let now = Instant::now();
+        let spent = now.duration_since(self.stage_started).as_millis() as u64;
+        match previous {
+            Some(Stage::Transcribing) => self.timings.transcribing_ms = spent,
+            Some(Stage::Thinking) => self.timings.thinking_ms = spent,
+            Some(Stage::Speaking) => self.timings.speaking_ms = spent,
+            _ => {}
+        }
+        self.stage_started = now;
+
+        let finished = matches!(stage, Stage::Done | Stage::Cancelled | Stage::Failed);
+        if finished {
+            self.timings.total_ms = now.duration_since(self.started).as_millis() as u64;
+        }
+
+        let event = StageEvent {
+            turn_id: self.turn_id.clone(),
+            stage,
+            elapsed_ms: now.duration_since(self.started).as_millis() as u64,
+            timings: finished.then(|| self.timings.clone()),
+        };
+        println!("Turn {} entered {:?} after {}ms", self.turn_id, stage, event.elapsed_ms);
+        if let Err(e) = self.app_handle.emit("turn-stage", event) {
+            eprintln!("Failed to emit turn stage: {}", e);
+        }
+    }
+}
+
+/// Runs one voice turn: ASR → LLM → TTS → persistence, emitting `turn-stage`
+/// events along the way. Setting `cancel` aborts the turn at the next
+/// opportunity; Whisper can't be interrupted mid-decode, but generation and
+/// queued speech stop immediately.
+pub async fn run(
+    turn_id: String,
+    conversation_id: String,
+    audio: TurnAudio,
+    services: TurnServices,
+    cancel: watch::Receiver<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<TurnResult, AppError> {
+    let mut clock = StageClock::start(&turn_id, &app_handle);
+    let cancelled = |clock: &mut StageClock, previous: Stage, transcript: String, language: &str, response: String| {
+        clock.enter(Some(previous), Stage::Cancelled);
+        TurnResult {
+            turn_id: turn_id.clone(),
+            transcript,
+            language: language.to_string(),
+            response,
+            cancelled: true,
+            timings: clock.timings.clone(),
+        }
+    };
+    // Ends the turn on an error, so the frontend isn't left showing the stage forever
+    let failed = |clock: &mut StageClock, previous: Stage, error: AppError| {
+        clock.enter(Some(previous), Stage::Failed);
+        error
+    };
+
+    clock.enter(None, Stage::Transcribing);
+    let utterance = transcribe(audio, &conversation_id, &services, &app_handle)
+        .await
+        .map_err(|e| failed(&mut clock, Stage::Transcribing, e))?;
+    let transcript = utterance.transcript.text.clone();
+    let language = utterance.transcript.language.clone();
+    if *cancel.borrow() {
+        return Ok(cancelled(&mut clock, Stage::Transcribing, transcript, &language, String::new()));
+    }
+
+    // Answer, and speak, in the language the user just used
+    clock.enter(Some(Stage::Transcribing), Stage::Thinking);
+    let speech = SpeechQueue::start(
+        turn_id.clone(),
+        services.tts.clone(),
+        Some(language.clone()),
+        cancel.clone(),
+        app_handle.clone(),
+    );
+    let segments = speech.segments();
+    if let Err(e) = services.turns.begin(&turn_id, segments.clone()) {
+        let _ = speech.finish().await;
+        return Err(failed(&mut clock, Stage::Thinking, e));
+    }
+    let reply = chat::respond(
+        &conversation_id,
+        transcript.clone(),
+        chat::ReplyOptions {
+            request_id: &turn_id,
+            cancel: cancel.clone(),
+            speech: Some(&speech),
+            token_budget: services.settings.context_token_budget,
+            language: Some(&language),
+        },
+        &app_handle,
+    )
+    .await;
+
+    let reply = match reply {
+        Ok(reply) => reply,
+        Err(e) => {
+            // Let the speech worker wind down; there is nothing left to say
+            let _ = speech.finish().await;
+            services.turns.spent(clip_paths(&segments));
+            return Err(failed(&mut clock, Stage::Thinking, e));
+        }
+    };
+
+    if let Some(message_id) = reply.message_id.clone() {
+        let interruption = services.turns.update(&turn_id, |record| record.saved(message_id)).and_then(|interruption| match interruption {
+            Some((message_id, spoken)) => app_handle.state::<Database>().record_interruption(&message_id, Some(&spoken)),
+            None => Ok(()),
+        });
+        if let Err(e) = interruption {
+            let _ = speech.finish().await;
+            return Err(failed(&mut clock, Stage::Thinking, e));
+        }
+    }
+
+    let tts_engine = services.tts.name();
+    if reply.outcome.cancelled {
+        let _ = speech.finish().await;
+        let result = cancelled(&mut clock, Stage::Thinking, transcript, &language, reply.outcome.text.clone());
+        persist(&reply, utterance, &segments, &services.turns, tts_engine, &result.timings, &app_handle).await;
+        return Ok(result);
+    }
+
+    // The remaining sentences are still being synthesized
+    clock.enter(Some(Stage::Thinking), Stage::Speaking);
+    if let Err(e) = speech.finish().await {
+        return Err(failed(&mut clock, Stage::Speaking, e));
+    }
+    if *cancel.borrow() {
+        let result = cancelled(&mut clock, Stage::Speaking, transcript, &language, reply.outcome.text.clone());
+        persist(&reply, utterance, &segments, &services.turns, tts_engine, &result.timings, &app_handle).await;
+        return Ok(result);
+    }
+
+    clock.enter(Some(Stage::Speaking), Stage::Done);
+    persist(&reply, utterance, &segments, &services.turns, tts_engine, &clock.timings, &app_handle).await;
+    Ok(TurnResult {
+        turn_id,
+        transcript,
+        language,
+        response: reply.outcome.text,
+        cancelled: false,
+        timings: clock.timings,
+    })
+}
+
+// Keeps the turn's audio and diagnostics with its messages: the user's
+// recording, the reply's clips joined into one file, and what produced them.
+// Failures are only logged; the conversation itself is already saved.
+async fn persist(
+    reply: &Reply,
+    utterance: Utterance,
+    segments: &Mutex<Vec<SpokenSegment>>,
+    turns: &Turns,
+    tts_engine: &str,
+    timings: &TurnTimings,
+    app_handle: &tauri::AppHandle,
+) {
+    let clips = clip_paths(segments);
+    let user_message_id = reply.user_message_id.clone();
+    let message_id = reply.message_id.clone();
+    let user_metadata = MessageMetadata {
+        segment_confidences: Some(utterance.transcript.segment_confidences()),
+        asr_model: Some(utterance.transcript.model).filter(|model| !model.is_empty()),
+        segments: Some(utterance.transcript.segments),
+        timings: Some(timings.clone()),
+        ..Default::default()
+    };
+    let reply_metadata = MessageMetadata {
+        tts_engine: Some(tts_engine.to_string()),
+        timings: Some(timings.clone()),
+        ..chat::reply_metadata(&reply.outcome)
+    };
+    let samples = utterance.samples;
+    let app_handle = app_handle.clone();
+
+    let stored = tokio::task::spawn_blocking(move || -> Result<(), AppError> {
+        let db = app_handle.state::<Database>();
+        let store = app_handle.state::<AudioStore>();
+
+        let recording = store.store_samples(&samples, asr::SAMPLE_RATE)?;
+        db.set_message_audio(&user_message_id, &recording)?;
+        db.set_message_metadata(&user_message_id, &MessageMetadata {
+            audio_duration_ms: Some(recording.duration_ms),
+            ..user_metadata
+        })?;
+
+        if let Some(message_id) = message_id {
+            let mut metadata = reply_metadata;
+            if let Some(speech) = store.store_clips(&clips)? {
+                db.set_message_audio(&message_id, &speech)?;
+                metadata.audio_duration_ms = Some(speech.duration_ms);
+            }
+            db.set_message_metadata(&message_id, &metadata)?;
+        }
+
+        Ok(())
+    })
+    .await
+    .context(AppError::Internal, "Task failed");
+
+    if let Err(e) = stored.and_then(|result| result) {
+        eprintln!("Failed to store turn audio: {}", e);
+    }
+    turns.spent(clip_paths(segments));
+}
+
+/// Paths of the clips synthesized for `segments`.
+pub fn clip_paths(segments: &Mutex<Vec<SpokenSegment>>) -> Vec<String> {
+    match segments.lock() {
+        Ok(segments) => segments.iter().map(|segment| segment.path.clone()).collect(),
+        Err(poisoned) => poisoned.into_inner().iter().map(|segment| segment.path.clone()).collect(),
+    }
+}
+
+async fn transcribe(
+    audio: TurnAudio,
+    conversation_id: &str,
+    services: &TurnServices,
+    app_handle: &tauri::AppHandle,
+) -> Result<Utterance, AppError> {
+    let context = AsrContext::load(
+        &app_handle.state::<Database>(),
+        Some(conversation_id),
+        services.settings.asr_conversation_prompt,
+    )?;
+
+    match audio {
+        TurnAudio::Recording => {
+            let recorder = services.recorder.clone();
+            let session_id = tokio::task::spawn_blocking(move || recorder.stop())
+                .await
+                .context(AppError::Internal, "Task failed")??;
+
+            services.transcripts.finish(&session_id, context, app_handle).await
+        }
+        TurnAudio::Blob(audio_data) => {
+            let engine = services.asr.clone();
+            let allow_ffmpeg = services.settings.ffmpeg_fallback;
+            let app_handle = app_handle.clone();
+
+            tokio::task::spawn_blocking(move || {
+                let samples = audio_processing::process_audio_blob(audio_data, allow_ffmpeg)?;
+                let transcript = engine.transcribe(&samples, &context, &app_handle)?;
+                Ok(Utterance { transcript, samples })
+            })
+            .await
+            .context(AppError::Internal, "Task failed")?
+        }
+    }
+}
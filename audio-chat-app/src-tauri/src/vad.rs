@@ -0,0 +1,410 @@
+use realfft::{RealFftPlanner, RealToComplex};
+use std::ops::Range;
+use std::sync::Arc;
+
+const FRAME_MS: usize = 20;
+// Floor for frame energy so digital silence doesn't produce -inf
+const MIN_ENERGY_DB: f32 = -100.0;
+// How quickly the streaming noise floor follows non-speech frames
+const NOISE_ADAPT_RATE: f32 = 0.05;
+
+#[derive(Debug, Clone)]
+pub struct VadConfig {
+    /// A frame counts as speech when it is this much louder than the noise floor.
+    pub energy_threshold_db: f32,
+    /// Frames at least this loud are speech regardless of the noise estimate.
+    pub absolute_threshold_db: f32,
+    /// Spectral flatness above this looks like noise rather than voice (0..1).
+    pub max_flatness: f32,
+    /// Speech runs shorter than this are treated as clicks and dropped.
+    pub min_speech_ms: usize,
+    /// Pauses shorter than this don't split a segment.
+    pub min_silence_ms: usize,
+    /// Audio kept around each segment so word onsets aren't clipped.
+    pub padding_ms: usize,
+    /// Segments are only split on pauses once the recording exceeds this.
+    pub max_segment_ms: usize,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            energy_threshold_db: 12.0,
+            absolute_threshold_db: -30.0,
+            max_flatness: 0.5,
+            min_speech_ms: 120,
+            min_silence_ms: 600,
+            padding_ms: 200,
+            max_segment_ms: 30_000,
+        }
+    }
+}
+
+/// A span of speech, as sample indices into the analysed buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeechSegment {
+    pub start: usize,
+    pub end: usize,
+}
+
+struct FrameFeatures {
+    energy_db: f32,
+    flatness: f32,
+}
+
+struct FeatureExtractor {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+}
+
+impl FeatureExtractor {
+    fn new(frame_len: usize) -> Self {
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(frame_len);
+        // Hann window to keep spectral leakage from flattening the spectrum
+        let window = (0..frame_len)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame_len as f32).cos())
+            .collect();
+
+        Self { fft, window }
+    }
+
+    fn features(&self, frame: &[f32]) -> FrameFeatures {
+        let mean_square = frame.iter().map(|s| s * s).sum::<f32>() / frame.len().max(1) as f32;
+        let energy_db = (10.0 * mean_square.log10()).max(MIN_ENERGY_DB);
+
+        let mut input: Vec<f32> = frame.iter().zip(&self.window).map(|(s, w)| s * w).collect();
+        input.resize(self.window.len(), 0.0);
+        let mut spectrum = self.fft.make_output_vec();
+
+        let flatness = match self.fft.process(&mut input, &mut spectrum) {
+            Ok(()) => spectral_flatness(&spectrum.iter().skip(1).map(|c| c.norm_sqr()).collect::<Vec<_>>()),
+            Err(_) => 1.0,
+        };
+
+        FrameFeatures { energy_db, flatness }
+    }
+}
+
+// Geometric over arithmetic mean of the power spectrum: near 1 for white
+// noise, much lower for harmonic sounds like voiced speech.
+fn spectral_flatness(power: &[f32]) -> f32 {
+    const EPSILON: f32 = 1e-12;
+
+    if power.is_empty() {
+        return 1.0;
+    }
+
+    let log_mean = power.iter().map(|p| (p + EPSILON).ln()).sum::<f32>() / power.len() as f32;
+    let mean = power.iter().sum::<f32>() / power.len() as f32 + EPSILON;
+
+    (log_mean.exp() / mean).clamp(0.0, 1.0)
+}
+
+fn is_speech(features: &FrameFeatures, noise_floor_db: f32, config: &VadConfig) -> bool {
+    if features.energy_db >= config.absolute_threshold_db {
+        return true;
+    }
+
+    features.energy_db >= noise_floor_db + config.energy_threshold_db && features.flatness <= config.max_flatness
+}
+
+fn frame_len(sample_rate: u32) -> usize {
+    (sample_rate as usize * FRAME_MS / 1000).max(1)
+}
+
+fn ms_to_frames(ms: usize) -> usize {
+    ms.div_ceil(FRAME_MS)
+}
+
+/// Finds speech in a complete recording. The noise floor is estimated from
+/// the quietest frames, so it adapts to the room and microphone.
+pub fn detect_speech(samples: &[f32], sample_rate: u32, config: &VadConfig) -> Vec<SpeechSegment> {
+    let frame_len = frame_len(sample_rate);
+    let extractor = FeatureExtractor::new(frame_len);
+    let features: Vec<FrameFeatures> = samples.chunks(frame_len).map(|frame| extractor.features(frame)).collect();
+
+    if features.is_empty() {
+        return Vec::new();
+    }
+
+    let noise_floor_db = {
+        let mut energies: Vec<f32> = features.iter().map(|f| f.energy_db).collect();
+        energies.sort_by(|a, b| a.total_cmp(b));
+        energies[energies.len() / 10]
+    };
+
+    let speech: Vec<bool> = features.iter().map(|f| is_speech(f, noise_floor_db, config)).collect();
+
+    // Group speech frames into runs, bridging pauses shorter than min_silence_ms
+    let min_speech = ms_to_frames(config.min_speech_ms);
+    let min_silence = ms_to_frames(config.min_silence_ms);
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    let mut i = 0;
+    while i < speech.len() {
+        if !speech[i] {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < speech.len() && speech[i] {
+            i += 1;
+        }
+
+        match runs.last_mut() {
+            Some(last) if start - last.1 < min_silence => last.1 = i,
+            _ => runs.push((start, i)),
+        }
+    }
+
+    let padding = ms_to_frames(config.padding_ms);
+    runs.into_iter()
+        .filter(|(start, end)| end - start >= min_speech)
+        .map(|(start, end)| SpeechSegment {
+            start: start.saturating_sub(padding) * frame_len,
+            end: ((end + padding) * frame_len).min(samples.len()),
+        })
+        .collect()
+}
+
+/// Cuts leading and trailing silence. Returns `None` if there is no speech.
+pub fn trim_silence<'a>(samples: &'a [f32], sample_rate: u32, config: &VadConfig) -> Option<&'a [f32]> {
+    let segments = detect_speech(samples, sample_rate, config);
+    let start = segments.first()?.start;
+    let end = segments.last()?.end;
+    Some(&samples[start..end])
+}
+
+/// Splits a recording into chunks Whisper can handle well: silence is trimmed,
+/// and recordings longer than `max_segment_ms` are cut at pauses. Returns the
+/// sample range of each chunk.
+pub fn split_utterance(samples: &[f32], sample_rate: u32, config: &VadConfig) -> Vec<Range<usize>> {
+    let segments = detect_speech(samples, sample_rate, config);
+    let (first, last) = match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return Vec::new(),
+    };
+
+    let max_len = sample_rate as usize * config.max_segment_ms / 1000;
+    if last.end - first.start <= max_len {
+        return vec![Range { start: first.start, end: last.end }];
+    }
+
+    // Greedily pack consecutive segments so each chunk stays under the limit
+    let mut chunks = Vec::new();
+    let mut chunk = first;
+    for segment in segments.into_iter().skip(1) {
+        if segment.end - chunk.start <= max_len {
+            chunk.end = segment.end;
+        } else {
+            chunks.push(chunk.start..chunk.end);
+            chunk = segment;
+        }
+    }
+    chunks.push(chunk.start..chunk.end);
+
+    chunks
+}
+
+/// Streaming end-of-utterance detector for hands-free mode. Feed it audio as
+/// it arrives; it reports when speech was heard and then `end_silence_ms` of
+/// silence followed.
+pub struct EndpointDetector {
+    config: VadConfig,
+    extractor: FeatureExtractor,
+    frame_len: usize,
+    pending: Vec<f32>,
+    noise_floor_db: Option<f32>,
+    speech_frames: usize,
+    silence_frames: usize,
+    end_silence_frames: usize,
+}
+
+impl EndpointDetector {
+    pub fn new(sample_rate: u32, end_silence_ms: usize, config: VadConfig) -> Self {
+        let frame_len = frame_len(sample_rate);
+        Self {
+            config,
+            extractor: FeatureExtractor::new(frame_len),
+            frame_len,
+            pending: Vec::new(),
+            noise_floor_db: None,
+            speech_frames: 0,
+            silence_frames: 0,
+            end_silence_frames: ms_to_frames(end_silence_ms),
+        }
+    }
+
+    /// Returns true once the utterance has ended.
+    pub fn push(&mut self, samples: &[f32]) -> bool {
+        self.pending.extend_from_slice(samples);
+
+        let mut ended = false;
+        while self.pending.len() >= self.frame_len {
+            let frame: Vec<f32> = self.pending.drain(..self.frame_len).collect();
+            let features = self.extractor.features(&frame);
+
+            // Seed the noise floor from the first frame, then let quiet frames pull it along
+            let noise_floor_db = *self.noise_floor_db.get_or_insert(features.energy_db);
+
+            if is_speech(&features, noise_floor_db, &self.config) {
+                self.speech_frames += 1;
+                self.silence_frames = 0;
+            } else {
+                self.silence_frames += 1;
+                self.noise_floor_db = Some(noise_floor_db + NOISE_ADAPT_RATE * (features.energy_db - noise_floor_db));
+            }
+
+            if self.speech_started() && self.silence_frames >= self.end_silence_frames {
+                ended = true;
+            }
+        }
+
+        ended
+    }
+
+    /// Whether enough speech has been heard to count as the user talking.
+    pub fn speech_started(&self) -> bool {
+        self.speech_frames >= ms_to_frames(self.config.min_speech_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    // Deterministic white noise around -65 dB, like a quiet room
+    fn noise(ms: usize, seed: &mut u32) -> Vec<f32> {
+        (0..RATE as usize * ms / 1000)
+            .map(|_| {
+                *seed ^= *seed << 13;
+                *seed ^= *seed >> 17;
+                *seed ^= *seed << 5;
+                (*seed as f32 / u32::MAX as f32 * 2.0 - 1.0) * 0.001
+            })
+            .collect()
+    }
+
+    // A quiet 220 Hz tone over the same noise, standing in for voiced speech
+    fn tone(ms: usize, seed: &mut u32) -> Vec<f32> {
+        noise(ms, seed)
+            .into_iter()
+            .enumerate()
+            .map(|(i, n)| n + 0.02 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    // Alternating noise and tone, starting with noise; lengths in ms
+    fn recording(parts: &[usize]) -> Vec<f32> {
+        let mut seed = 0x2545_f491;
+        parts
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &ms)| if i % 2 == 0 { noise(ms, &mut seed) } else { tone(ms, &mut seed) })
+            .collect()
+    }
+
+    // Sample index `ms` into a recording
+    fn offset(ms: usize) -> usize {
+        RATE as usize * ms / 1000
+    }
+
+    #[test]
+    fn silence_has_no_speech() {
+        let config = VadConfig::default();
+        let samples = recording(&[2000]);
+
+        assert!(detect_speech(&samples, RATE, &config).is_empty());
+        assert!(trim_silence(&samples, RATE, &config).is_none());
+        assert!(split_utterance(&samples, RATE, &config).is_empty());
+
+        let mut detector = EndpointDetector::new(RATE, 800, config);
+        assert!(!detector.push(&samples));
+        assert!(!detector.speech_started());
+    }
+
+    #[test]
+    fn short_clicks_are_dropped() {
+        let config = VadConfig::default();
+        let samples = recording(&[1000, 60, 1000]);
+
+        assert!(detect_speech(&samples, RATE, &config).is_empty());
+        assert!(trim_silence(&samples, RATE, &config).is_none());
+
+        let mut detector = EndpointDetector::new(RATE, 800, config);
+        assert!(!detector.push(&samples));
+        assert!(!detector.speech_started());
+    }
+
+    #[test]
+    fn short_pauses_are_bridged() {
+        let config = VadConfig::default();
+        let padding = offset(config.padding_ms);
+
+        // 300 ms is under min_silence_ms, so both words are one segment
+        let bridged = recording(&[1000, 500, 300, 500, 1000]);
+        assert_eq!(
+            detect_speech(&bridged, RATE, &config),
+            vec![SpeechSegment { start: offset(1000) - padding, end: offset(2300) + padding }]
+        );
+
+        let split = recording(&[1000, 500, 1000, 500, 1000]);
+        assert_eq!(
+            detect_speech(&split, RATE, &config),
+            vec![
+                SpeechSegment { start: offset(1000) - padding, end: offset(1500) + padding },
+                SpeechSegment { start: offset(2500) - padding, end: offset(3000) + padding },
+            ]
+        );
+    }
+
+    #[test]
+    fn trims_leading_and_trailing_silence() {
+        let config = VadConfig::default();
+        let padding = offset(config.padding_ms);
+        let samples = recording(&[1000, 500, 1000, 500, 1000]);
+
+        let trimmed = trim_silence(&samples, RATE, &config).unwrap();
+        assert_eq!(trimmed, &samples[offset(1000) - padding..offset(3000) + padding]);
+    }
+
+    #[test]
+    fn splits_long_recordings_at_pauses() {
+        let padding = offset(VadConfig::default().padding_ms);
+        let samples = recording(&[1000, 500, 1000, 500, 1000]);
+
+        let whole = split_utterance(&samples, RATE, &VadConfig::default());
+        assert_eq!(whole, vec![offset(1000) - padding..offset(3000) + padding]);
+
+        let config = VadConfig { max_segment_ms: 1500, ..Default::default() };
+        assert_eq!(
+            split_utterance(&samples, RATE, &config),
+            vec![
+                offset(1000) - padding..offset(1500) + padding,
+                offset(2500) - padding..offset(3000) + padding,
+            ]
+        );
+    }
+
+    #[test]
+    fn endpoint_fires_after_end_silence() {
+        let samples = recording(&[500, 500, 2000]);
+        let mut detector = EndpointDetector::new(RATE, 800, VadConfig::default());
+
+        // Feed it as the recorder does, 100 ms at a time
+        let mut ended_at = None;
+        for (i, chunk) in samples.chunks(offset(100)).enumerate() {
+            if detector.push(chunk) && ended_at.is_none() {
+                ended_at = Some((i + 1) * 100);
+            }
+            if i < 5 {
+                assert!(!detector.speech_started());
+            }
+        }
+
+        assert!(detector.speech_started());
+        assert_eq!(ended_at, Some(500 + 500 + 800));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::db::{Database, Replacement};
+use crate::error::AppError;
+use crate::transcript::Transcript;
+
+/// Whisper only looks at the last 224 prompt tokens; at a few characters per
+/// token this keeps the prompt comfortably inside that.
+const MAX_PROMPT_CHARS: usize = 600;
+
+/// What Whisper should know before hearing an utterance, and the fix-ups to
+/// apply to what it hears.
+#[derive(Debug, Clone, Default)]
+pub struct AsrContext {
+    /// Passed to Whisper as the initial prompt: the vocabulary, then the end
+    /// of the conversation so far.
+    pub prompt: Option<String>,
+    pub replacements: Vec<Replacement>,
+}
+
+impl AsrContext {
+    /// Loads the vocabulary (every conversation's and `conversation_id`'s)
+    /// and the replacements. With `include_history`, recent messages of the
+    /// conversation are added to the prompt too.
+    pub fn load(db: &Database, conversation_id: Option<&str>, include_history: bool) -> Result<Self, AppError> {
+        let terms: Vec<String> = db
+            .get_vocabulary(conversation_id)?
+            .into_iter()
+            .map(|term| term.term)
+            .collect();
+
+        let history = match conversation_id {
+            Some(conversation_id) if include_history => db
+                .get_messages(conversation_id)?
+                .iter()
+                .map(|message| message.heard_text().to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => String::new(),
+        };
+
+        Ok(Self {
+            prompt: build_prompt(&terms, &history),
+            replacements: db.get_replacements()?,
+        })
+    }
+
+    /// Applies the replacements to the text, segments and words of `transcript`.
+    pub fn apply(&self, transcript: &mut Transcript) {
+        if self.replacements.is_empty() {
+            return;
+        }
+
+        transcript.text = replace(&transcript.text, &self.replacements);
+        for segment in &mut transcript.segments {
+            segment.text = replace(&segment.text, &self.replacements);
+            // Only replacements within a single word can match here
+            for word in &mut segment.words {
+                word.text = replace(&word.text, &self.replacements);
+            }
+        }
+    }
+}
+
+// Vocabulary first, as a glossary sentence, then as much of the most recent
+// conversation as still fits, since Whisper weighs the end of the prompt most
+fn build_prompt(terms: &[String], history: &str) -> Option<String> {
+    let mut prompt = String::new();
+    if !terms.is_empty() {
+        prompt.push_str(&format!("Glossary: {}.", terms.join(", ")));
+    }
+
+    let room = MAX_PROMPT_CHARS.saturating_sub(prompt.chars().count() + 1);
+    let history = history.trim();
+    let skip = history.chars().count().saturating_sub(room);
+    if skip < history.chars().count() {
+        let tail: String = history.chars().skip(skip).collect();
+        // Don't start in the middle of a word
+        let tail = match tail.split_once(' ') {
+            Some((_, rest)) if skip > 0 => rest.to_string(),
+            _ => tail,
+        };
+        if !prompt.is_empty() {
+            prompt.push(' ');
+        }
+        prompt.push_str(&tail);
+    }
+
+    // whisper.cpp takes a C string
+    let prompt = prompt.replace('\0', "");
+    (!prompt.trim().is_empty()).then_some(prompt)
+}
+
+/// Replaces whole-word, case-insensitive occurrences of each `from`.
+fn replace(text: &str, replacements: &[Replacement]) -> String {
+    let mut text = text.to_string();
+    for replacement in replacements {
+        text = replace_one(&text, &replacement.from, &replacement.to);
+    }
+    text
+}
+
+fn replace_one(text: &str, from: &str, to: &str) -> String {
+    // Lowercased char by char so positions line up with the original text
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let from: Vec<char> = from.chars().map(fold).collect();
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars.iter().copied().map(fold).collect();
+    if from.is_empty() {
+        return text.to_string();
+    }
+
+    let is_word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let matches = lower[i..].starts_with(&from)
+            && !is_word(i.checked_sub(1).and_then(|j| chars.get(j)))
+            && !is_word(chars.get(i + from.len()));
+        if matches {
+            result.push_str(to);
+            i += from.len();
+        } else {
+            result.push(chars[i]);
+            i += 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_whole_words_ignoring_case() {
+        assert_eq!(replace_one("ask olama, then Olama again", "olama", "Ollama"), "ask Ollama, then Ollama again");
+        assert_eq!(replace_one("OLAMA.", "olama", "Ollama"), "Ollama.");
+        assert_eq!(replace_one("my cube control setup", "cube control", "kubectl"), "my kubectl setup");
+    }
+
+    #[test]
+    fn leaves_matches_inside_words_alone() {
+        assert_eq!(replace_one("catalog concat cat", "cat", "dog"), "catalog concat dog");
+        assert_eq!(replace_one("cat2 2cat", "cat", "dog"), "cat2 2cat");
+        assert_eq!(replace_one("anything", "", "x"), "anything");
+    }
+
+    #[test]
+    fn caps_prompt_length() {
+        let terms = vec!["Tauri".to_string(), "whisper.cpp".to_string()];
+        let history = "word ".repeat(500);
+
+        let prompt = build_prompt(&terms, &history).unwrap();
+        assert!(prompt.chars().count() <= MAX_PROMPT_CHARS, "{}", prompt.chars().count());
+        assert!(prompt.starts_with("Glossary: Tauri, whisper.cpp. word"));
+        // The most recent history is kept, cut at a word boundary
+        assert!(prompt.ends_with(" word"));
+        assert!(!prompt.contains(" ord"));
+
+        let prompt = build_prompt(&[], "short history").unwrap();
+        assert_eq!(prompt, "short history");
+        assert_eq!(build_prompt(&[], "  "), None);
+    }
+}
@@ -3,6 +3,7 @@ import VoiceChat from "./components/VoiceChat";
 import HistorySidebar from "./components/HistorySidebar";
 import StatusBar from "./components/StatusBar";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 export interface Message {
   id: string;
@@ -10,11 +11,64 @@ export interface Message {
   role: "user" | "assistant";
   content: string;
   timestamp: string;
+  // For interrupted replies, the part the user actually heard
+  spoken_content: string | null;
+  interrupted: boolean;
+  // ISO 639-1 code of the spoken language, e.g. "de"
+  language: string | null;
+  // Recording or spoken reply in the audio store, if kept
+  audio_file: string | null;
+  audio_sha256: string | null;
+  metadata: MessageMetadata | null;
+}
+
+export interface TranscriptWord {
+  text: string;
+  start_ms: number;
+  end_ms: number;
+  confidence: number;
+  tokens: { text: string; probability: number }[];
+}
+
+export interface TranscriptSegment {
+  text: string;
+  start_ms: number;
+  end_ms: number;
+  confidence: number;
+  // Whisper was unsure of the whole segment
+  low_confidence?: boolean;
+  words: TranscriptWord[];
+}
+
+// How a message was produced; fields depend on the role and how far the turn got
+export interface MessageMetadata {
+  audio_duration_ms: number | null;
+  asr_model: string | null;
+  segment_confidences: number[] | null;
+  // Whisper segments with word timings (ms into the recording) and confidences
+  segments: TranscriptSegment[] | null;
+  llm_model: string | null;
+  prompt_tokens: number | null;
+  completion_tokens: number | null;
+  tts_engine: string | null;
+  timings: {
+    transcribing_ms: number;
+    thinking_ms: number;
+    speaking_ms: number;
+    total_ms: number;
+  } | null;
 }
 
 export interface Conversation {
   id: string;
   created_at: string;
+  title: string | null;
+  // Time of the last message
+  updated_at: string;
+  archived: boolean;
+  pinned: boolean;
+  // Generated once a conversation gets long
+  summary: string | null;
 }
 
 function App() {
@@ -23,13 +77,23 @@ function App() {
   const [messages, setMessages] = useState<Message[]>([]);
   const [sidebarOpen, setSidebarOpen] = useState(false);
   const [ollamaStatus, setOllamaStatus] = useState<boolean>(false);
+  const [showArchived, setShowArchived] = useState(false);
 
   // Check Ollama status on startup
   useEffect(() => {
     checkOllamaStatus();
-    loadConversations();
   }, []);
 
+  useEffect(() => {
+    loadConversations();
+
+    // Titles and summaries are generated in the background after a reply
+    const unlisten = listen("conversation-updated", () => loadConversations());
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [showArchived]);
+
   const checkOllamaStatus = async () => {
     try {
       const status = await invoke<boolean>("check_ollama");
@@ -42,7 +106,10 @@ function App() {
 
   const loadConversations = async () => {
     try {
-      const convs = await invoke<Conversation[]>("get_conversations");
+      const convs = await invoke<Conversation[]>("get_conversations", {
+        sort: "last_activity",
+        includeArchived: showArchived,
+      });
       setConversations(convs);
     } catch (error) {
       console.error("Failed to load conversations:", error);
@@ -71,25 +138,9 @@ function App() {
     }
   };
 
-  const saveMessage = async (role: "user" | "assistant", content: string) => {
-    if (!currentConversationId) {
-      await createNewConversation();
-    }
-    
-    try {
-      await invoke("save_message", {
-        conversationId: currentConversationId,
-        role,
-        content,
-      });
-      
-      // Reload messages to show the new one
-      if (currentConversationId) {
-        await loadMessages(currentConversationId);
-      }
-    } catch (error) {
-      console.error("Failed to save message:", error);
-    }
+  const refreshMessages = async (conversationId: string) => {
+    await loadMessages(conversationId);
+    await loadConversations();
   };
 
   const selectConversation = async (conversationId: string) => {
@@ -98,6 +149,46 @@ function App() {
     setSidebarOpen(false);
   };
 
+  const renameConversation = async (conversationId: string, title: string) => {
+    try {
+      await invoke("rename_conversation", { conversationId, title });
+      await loadConversations();
+    } catch (error) {
+      console.error("Failed to rename conversation:", error);
+    }
+  };
+
+  const pinConversation = async (conversationId: string, pinned: boolean) => {
+    try {
+      await invoke("pin_conversation", { conversationId, pinned });
+      await loadConversations();
+    } catch (error) {
+      console.error("Failed to pin conversation:", error);
+    }
+  };
+
+  const archiveConversation = async (conversationId: string, archived: boolean) => {
+    try {
+      await invoke("archive_conversation", { conversationId, archived });
+      await loadConversations();
+    } catch (error) {
+      console.error("Failed to archive conversation:", error);
+    }
+  };
+
+  const deleteConversation = async (conversationId: string) => {
+    try {
+      await invoke("delete_conversation", { conversationId });
+      if (conversationId === currentConversationId) {
+        setCurrentConversationId(null);
+        setMessages([]);
+      }
+      await loadConversations();
+    } catch (error) {
+      console.error("Failed to delete conversation:", error);
+    }
+  };
+
   return (
     <div className="flex h-screen bg-gradient-to-br from-blue-900 via-purple-900 to-indigo-900">
       {/* Sidebar */}
@@ -108,6 +199,13 @@ function App() {
         currentConversationId={currentConversationId}
         onSelectConversation={selectConversation}
         onNewConversation={createNewConversation}
+        onRenameConversation={renameConversation}
+        onPinConversation={pinConversation}
+        onArchiveConversation={archiveConversation}
+        onDeleteConversation={deleteConversation}
+        showArchived={showArchived}
+        onShowArchivedChange={setShowArchived}
+        onConversationsImported={loadConversations}
       />
 
       {/* Main Content */}
@@ -132,7 +230,7 @@ function App() {
         <div className="flex-1 flex items-center justify-center">
           <VoiceChat
             currentConversationId={currentConversationId}
-            onSaveMessage={saveMessage}
+            onMessagesChanged={refreshMessages}
             onCreateConversation={createNewConversation}
             messages={messages}
           />
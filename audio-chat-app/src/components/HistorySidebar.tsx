@@ -1,4 +1,31 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { Conversation } from "../App";
+import { toAppError } from "../errors";
+
+type ExportFormat = "json" | "markdown" | "bundle";
+
+interface SearchHit {
+  message_id: string;
+  conversation_id: string;
+  conversation_title: string | null;
+  role: "user" | "assistant";
+  timestamp: string;
+  // Matched terms are wrapped in <mark></mark>; the text is not escaped
+  snippet: string;
+}
+
+// Renders a search snippet without interpreting the message text as HTML
+function Snippet({ text }: { text: string }) {
+  const parts = text.split(/<mark>|<\/mark>/);
+  return (
+    <>
+      {parts.map((part, i) =>
+        i % 2 === 1 ? <mark key={i} className="bg-yellow-400/40 text-white rounded px-0.5">{part}</mark> : part
+      )}
+    </>
+  );
+}
 
 interface HistorySidebarProps {
   isOpen: boolean;
@@ -7,6 +34,13 @@ interface HistorySidebarProps {
   currentConversationId: string | null;
   onSelectConversation: (id: string) => void;
   onNewConversation: () => void;
+  onRenameConversation: (id: string, title: string) => void;
+  onPinConversation: (id: string, pinned: boolean) => void;
+  onArchiveConversation: (id: string, archived: boolean) => void;
+  onDeleteConversation: (id: string) => void;
+  showArchived: boolean;
+  onShowArchivedChange: (showArchived: boolean) => void;
+  onConversationsImported: () => void;
 }
 
 export default function HistorySidebar({
@@ -16,7 +50,88 @@ export default function HistorySidebar({
   currentConversationId,
   onSelectConversation,
   onNewConversation,
+  onRenameConversation,
+  onPinConversation,
+  onArchiveConversation,
+  onDeleteConversation,
+  showArchived,
+  onShowArchivedChange,
+  onConversationsImported,
 }: HistorySidebarProps) {
+  const [editingId, setEditingId] = useState<string | null>(null);
+  const [draftTitle, setDraftTitle] = useState("");
+  const [query, setQuery] = useState("");
+  const [roleFilter, setRoleFilter] = useState<"" | "user" | "assistant">("");
+  const [fromDate, setFromDate] = useState("");
+  const [toDate, setToDate] = useState("");
+  const [hits, setHits] = useState<SearchHit[]>([]);
+  const [transferStatus, setTransferStatus] = useState<string | null>(null);
+
+  // Exports go to the Downloads folder
+  const exportConversations = async (format: ExportFormat, conversationId?: string) => {
+    try {
+      const path = await invoke<string>("export_conversations", { conversationId, format });
+      setTransferStatus(`Exported to ${path}`);
+    } catch (error) {
+      setTransferStatus(toAppError(error).detail);
+    }
+  };
+
+  const importConversations = async (file: File) => {
+    try {
+      const data = Array.from(new Uint8Array(await file.arrayBuffer()));
+      const summary = await invoke<{ conversations: number; messages: number }>("import_conversations", { data });
+      setTransferStatus(`Imported ${summary.conversations} conversations with ${summary.messages} messages`);
+      onConversationsImported();
+    } catch (error) {
+      setTransferStatus(toAppError(error).detail);
+    }
+  };
+
+  // Search as the user types, after a short pause
+  useEffect(() => {
+    if (!query.trim()) {
+      setHits([]);
+      return;
+    }
+
+    const timer = setTimeout(async () => {
+      try {
+        const results = await invoke<SearchHit[]>("search_messages", {
+          query,
+          filters: {
+            role: roleFilter || null,
+            from: fromDate ? new Date(`${fromDate}T00:00:00`).toISOString() : null,
+            to: toDate ? new Date(`${toDate}T23:59:59.999`).toISOString() : null,
+          },
+        });
+        setHits(results);
+      } catch (error) {
+        console.error("Search failed:", error);
+      }
+    }, 200);
+
+    return () => clearTimeout(timer);
+  }, [query, roleFilter, fromDate, toDate]);
+
+  const startRename = (conversation: Conversation) => {
+    setEditingId(conversation.id);
+    setDraftTitle(conversation.title ?? "");
+  };
+
+  const finishRename = () => {
+    if (editingId && draftTitle.trim()) {
+      onRenameConversation(editingId, draftTitle);
+    }
+    setEditingId(null);
+  };
+
+  const confirmDelete = (conversation: Conversation) => {
+    if (window.confirm(`Delete "${conversation.title ?? "Conversation"}" and all of its messages?`)) {
+      onDeleteConversation(conversation.id);
+    }
+  };
+
   const formatDate = (dateString: string) => {
     const date = new Date(dateString);
     return date.toLocaleDateString() + " " + date.toLocaleTimeString([], { 
@@ -73,9 +188,59 @@ export default function HistorySidebar({
             </button>
           </div>
 
-          {/* Conversations List */}
+          {/* Search */}
+          <div className="p-4 border-b border-gray-700 space-y-2">
+            <input
+              type="search"
+              value={query}
+              onChange={(e) => setQuery(e.target.value)}
+              placeholder="Search messages..."
+              className="w-full bg-gray-800 text-white text-sm rounded-lg px-3 py-2 placeholder-gray-500"
+            />
+            {query && (
+              <div className="flex items-center space-x-2 text-xs text-gray-400">
+                <select
+                  value={roleFilter}
+                  onChange={(e) => setRoleFilter(e.target.value as "" | "user" | "assistant")}
+                  className="bg-gray-800 rounded px-1 py-1"
+                >
+                  <option value="">Anyone</option>
+                  <option value="user">You</option>
+                  <option value="assistant">Assistant</option>
+                </select>
+                <input type="date" value={fromDate} onChange={(e) => setFromDate(e.target.value)} className="bg-gray-800 rounded px-1 py-1 w-28" />
+                <span>–</span>
+                <input type="date" value={toDate} onChange={(e) => setToDate(e.target.value)} className="bg-gray-800 rounded px-1 py-1 w-28" />
+              </div>
+            )}
+          </div>
+
+          {/* Conversations List, or search results while searching */}
           <div className="flex-1 overflow-y-auto">
-            {conversations.length === 0 ? (
+            {query.trim() ? (
+              hits.length === 0 ? (
+                <div className="p-4 text-center text-gray-400">
+                  <p>No matching messages</p>
+                </div>
+              ) : (
+                <div className="space-y-1 p-2">
+                  {hits.map((hit) => (
+                    <button
+                      key={hit.message_id}
+                      onClick={() => onSelectConversation(hit.conversation_id)}
+                      className="w-full text-left p-3 rounded-lg text-gray-300 hover:bg-gray-800 transition-colors"
+                    >
+                      <p className="text-xs opacity-75">
+                        {hit.conversation_title ?? "Conversation"} · {hit.role === "user" ? "You" : "Assistant"} · {formatDate(hit.timestamp)}
+                      </p>
+                      <p className="text-sm mt-1">
+                        <Snippet text={hit.snippet} />
+                      </p>
+                    </button>
+                  ))}
+                </div>
+              )
+            ) : conversations.length === 0 ? (
               <div className="p-4 text-center text-gray-400">
                 <p>No conversations yet</p>
                 <p className="text-sm mt-1">Start by recording your first message</p>
@@ -83,39 +248,104 @@ export default function HistorySidebar({
             ) : (
               <div className="space-y-1 p-2">
                 {conversations.map((conversation) => (
-                  <button
+                  <div
                     key={conversation.id}
-                    onClick={() => onSelectConversation(conversation.id)}
+                    onClick={() => editingId !== conversation.id && onSelectConversation(conversation.id)}
                     className={`
-                      w-full text-left p-3 rounded-lg transition-colors
+                      group w-full text-left p-3 rounded-lg transition-colors cursor-pointer
                       ${currentConversationId === conversation.id
                         ? "bg-blue-600 text-white"
                         : "text-gray-300 hover:bg-gray-800"
                       }
+                      ${conversation.archived ? "opacity-60" : ""}
                     `}
                   >
                     <div className="flex items-center justify-between">
                       <div className="flex-1 min-w-0">
-                        <p className="text-sm font-medium truncate">
-                          Conversation
-                        </p>
+                        {editingId === conversation.id ? (
+                          <input
+                            autoFocus
+                            value={draftTitle}
+                            onChange={(e) => setDraftTitle(e.target.value)}
+                            onBlur={finishRename}
+                            onKeyDown={(e) => {
+                              if (e.key === "Enter") finishRename();
+                              if (e.key === "Escape") setEditingId(null);
+                            }}
+                            onClick={(e) => e.stopPropagation()}
+                            className="w-full bg-gray-800 text-white text-sm rounded px-2 py-1"
+                          />
+                        ) : (
+                          <p className="text-sm font-medium truncate">
+                            {conversation.pinned && "📌 "}
+                            {conversation.title ?? "Conversation"}
+                          </p>
+                        )}
+                        {conversation.summary && (
+                          <p className="text-xs opacity-75 mt-1 line-clamp-2" title={conversation.summary}>
+                            {conversation.summary}
+                          </p>
+                        )}
                         <p className="text-xs opacity-75 mt-1">
-                          {formatDate(conversation.created_at)}
+                          {formatDate(conversation.updated_at)}
+                          {conversation.archived && " · Archived"}
                         </p>
                       </div>
-                      
+
+                      {/* Actions, shown on hover */}
+                      <div
+                        className="ml-2 hidden group-hover:flex items-center space-x-1 text-xs"
+                        onClick={(e) => e.stopPropagation()}
+                      >
+                        <button onClick={() => startRename(conversation)} title="Rename" className="hover:text-white">✏️</button>
+                        <button onClick={() => onPinConversation(conversation.id, !conversation.pinned)} title={conversation.pinned ? "Unpin" : "Pin"} className="hover:text-white">📌</button>
+                        <button onClick={() => exportConversations("markdown", conversation.id)} title="Export as Markdown" className="hover:text-white">⬇️</button>
+                        <button onClick={() => onArchiveConversation(conversation.id, !conversation.archived)} title={conversation.archived ? "Unarchive" : "Archive"} className="hover:text-white">🗄️</button>
+                        <button onClick={() => confirmDelete(conversation)} title="Delete" className="hover:text-white">🗑️</button>
+                      </div>
+
                       {currentConversationId === conversation.id && (
-                        <div className="ml-2 w-2 h-2 bg-white rounded-full"></div>
+                        <div className="ml-2 w-2 h-2 bg-white rounded-full group-hover:hidden"></div>
                       )}
                     </div>
-                  </button>
+                  </div>
                 ))}
               </div>
             )}
           </div>
 
           {/* Footer */}
-          <div className="p-4 border-t border-gray-700">
+          <div className="p-4 border-t border-gray-700 space-y-2">
+            <div className="flex items-center justify-center space-x-2 text-xs text-gray-400">
+              <span>Export all:</span>
+              <button onClick={() => exportConversations("json")} className="hover:text-white underline">JSON</button>
+              <button onClick={() => exportConversations("markdown")} className="hover:text-white underline">Markdown</button>
+              <button onClick={() => exportConversations("bundle")} className="hover:text-white underline">Zip</button>
+              <label className="hover:text-white underline cursor-pointer">
+                Import
+                <input
+                  type="file"
+                  accept=".json,.zip"
+                  className="hidden"
+                  onChange={(e) => {
+                    const file = e.target.files?.[0];
+                    if (file) importConversations(file);
+                    e.target.value = "";
+                  }}
+                />
+              </label>
+            </div>
+            {transferStatus && (
+              <p className="text-xs text-gray-400 text-center break-all">{transferStatus}</p>
+            )}
+            <label className="flex items-center justify-center space-x-2 text-xs text-gray-400">
+              <input
+                type="checkbox"
+                checked={showArchived}
+                onChange={(e) => onShowArchivedChange(e.target.checked)}
+              />
+              <span>Show archived</span>
+            </label>
             <p className="text-xs text-gray-400 text-center">
               Audio Chat App v0.1.0
             </p>
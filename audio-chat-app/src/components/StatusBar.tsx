@@ -1,9 +1,132 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { toAppError } from "../errors";
+
+interface InputDevice {
+  name: string;
+  is_default: boolean;
+  is_selected: boolean;
+}
+
+interface WhisperModel {
+  id: string;
+  size: string;
+  english_only: boolean;
+  quantization: string | null;
+  size_mb: number;
+  installed: boolean;
+  is_selected: boolean;
+  is_loaded: boolean;
+}
+
+interface AsrProfile {
+  profile: "fast" | "balanced" | "accurate" | "custom";
+  options: {
+    beam_size: number;
+    temperature_inc: number;
+    threads: number;
+  };
+  is_selected: boolean;
+}
+
 interface StatusBarProps {
   ollamaStatus: boolean;
   onRefreshOllama: () => void;
 }
 
 export default function StatusBar({ ollamaStatus, onRefreshOllama }: StatusBarProps) {
+  const [devices, setDevices] = useState<InputDevice[]>([]);
+  const [selectedDevice, setSelectedDevice] = useState<string>("");
+  const [deviceNotice, setDeviceNotice] = useState<string | null>(null);
+  const [models, setModels] = useState<WhisperModel[]>([]);
+  const [modelNotice, setModelNotice] = useState<string | null>(null);
+  const [profiles, setProfiles] = useState<AsrProfile[]>([]);
+
+  const loadDevices = async () => {
+    try {
+      const list = await invoke<InputDevice[]>("list_input_devices");
+      setDevices(list);
+      setSelectedDevice(list.find((d) => d.is_selected)?.name ?? "");
+    } catch (error) {
+      console.error("Failed to list input devices:", error);
+    }
+  };
+
+  const loadModels = async () => {
+    try {
+      setModels(await invoke<WhisperModel[]>("list_whisper_models"));
+    } catch (error) {
+      console.error("Failed to list Whisper models:", error);
+    }
+  };
+
+  const loadProfiles = async () => {
+    try {
+      setProfiles(await invoke<AsrProfile[]>("list_asr_profiles"));
+    } catch (error) {
+      console.error("Failed to list ASR profiles:", error);
+    }
+  };
+
+  // Keep the device list in sync with hot-plug events from the backend
+  useEffect(() => {
+    loadDevices();
+    loadModels();
+    loadProfiles();
+
+    const unlistenModel = listen("asr-model-loaded", () => {
+      loadModels();
+    });
+    const unlistenChanged = listen("input-devices-changed", () => {
+      loadDevices();
+    });
+    const unlistenLost = listen<{ name: string; fallback: string | null }>("input-device-lost", (event) => {
+      const fallback = event.payload.fallback ?? "no microphone";
+      setDeviceNotice(`${event.payload.name} disconnected, using ${fallback}`);
+    });
+
+    return () => {
+      unlistenChanged.then((fn) => fn());
+      unlistenLost.then((fn) => fn());
+      unlistenModel.then((fn) => fn());
+    };
+  }, []);
+
+  const selectDevice = async (name: string) => {
+    try {
+      await invoke("select_input_device", { name: name || null });
+      setSelectedDevice(name);
+      setDeviceNotice(null);
+    } catch (error) {
+      console.error("Failed to select input device:", error);
+      setDeviceNotice(toAppError(error).detail);
+    }
+  };
+
+  const selectModel = async (id: string) => {
+    try {
+      await invoke("select_whisper_model", { model: id });
+      setModelNotice(null);
+      await loadModels();
+    } catch (error) {
+      console.error("Failed to select Whisper model:", error);
+      setModelNotice(toAppError(error).message);
+    }
+  };
+
+  const selectProfile = async (profile: string) => {
+    try {
+      await invoke("select_asr_profile", { profile });
+      await loadProfiles();
+    } catch (error) {
+      console.error("Failed to select ASR profile:", error);
+      setModelNotice(toAppError(error).message);
+    }
+  };
+
+  const selectedModel = models.find((m) => m.is_selected);
+
   return (
     <div className="bg-gray-900/50 border-t border-gray-700 p-3">
       <div className="flex items-center justify-between text-sm">
@@ -25,8 +148,52 @@ export default function StatusBar({ ollamaStatus, onRefreshOllama }: StatusBarPr
 
         {/* Microphone Status */}
         <div className="flex items-center space-x-2">
-          <div className="w-2 h-2 rounded-full bg-green-500" />
-          <span className="text-gray-300">Microphone Ready</span>
+          <div className={`w-2 h-2 rounded-full ${deviceNotice ? 'bg-yellow-500' : 'bg-green-500'}`} />
+          <select
+            value={selectedDevice}
+            onChange={(e) => selectDevice(e.target.value)}
+            title={deviceNotice ?? "Microphone"}
+            className="bg-gray-800 text-gray-300 rounded px-1 max-w-48 truncate"
+          >
+            <option value="">System default</option>
+            {devices.map((device) => (
+              <option key={device.name} value={device.name}>
+                {device.name}{device.is_default ? " (default)" : ""}
+              </option>
+            ))}
+          </select>
+        </div>
+
+        {/* Whisper Model */}
+        <div className="flex items-center space-x-2">
+          <div className={`w-2 h-2 rounded-full ${
+            modelNotice ? 'bg-red-500' : selectedModel?.is_loaded ? 'bg-green-500' : 'bg-yellow-500'
+          }`} />
+          <select
+            value={selectedModel?.id ?? ""}
+            onChange={(e) => selectModel(e.target.value)}
+            title={modelNotice ?? (selectedModel?.is_loaded ? "Whisper model" : "Whisper model (loading)")}
+            className="bg-gray-800 text-gray-300 rounded px-1 max-w-48 truncate"
+          >
+            {models.map((model) => (
+              <option key={model.id} value={model.id} disabled={!model.installed}>
+                {model.id}{model.english_only ? "" : " (multilingual)"}
+                {model.installed ? "" : ` - not downloaded, ${model.size_mb}MB`}
+              </option>
+            ))}
+          </select>
+          <select
+            value={profiles.find((p) => p.is_selected)?.profile ?? ""}
+            onChange={(e) => selectProfile(e.target.value)}
+            title="Decoding profile"
+            className="bg-gray-800 text-gray-300 rounded px-1"
+          >
+            {profiles.map(({ profile, options }) => (
+              <option key={profile} value={profile}>
+                {profile}{options.beam_size > 1 ? ` (beam ${options.beam_size})` : ""}
+              </option>
+            ))}
+          </select>
         </div>
 
         {/* Help Text */}
@@ -1,19 +1,46 @@
 import { useState, useRef, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { Message } from "../App";
+import { AppError, RECOVERY_HINTS, toAppError } from "../errors";
 import Waveform from "./Waveform";
 import MicrophonePermissionModal from "./MicrophonePermissionModal";
 
+const STAGE_LABELS: Record<string, string> = {
+  transcribing: "📝 Transcribing...",
+  thinking: "🤔 Thinking...",
+  speaking: "🔊 Preparing speech...",
+};
+
+// Words Whisper was less sure of than this are highlighted
+const LOW_CONFIDENCE = 0.5;
+
+interface AudioRange {
+  startMs: number;
+  endMs: number;
+}
+
+interface TurnResult {
+  turn_id: string;
+  transcript: string;
+  language: string;
+  response: string;
+  cancelled: boolean;
+  timings: { transcribing_ms: number; thinking_ms: number; speaking_ms: number; total_ms: number };
+}
+
+const MICROPHONE_DENIED = "Microphone access denied. Please enable microphone permissions in System Settings.";
+
 interface VoiceChatProps {
   currentConversationId: string | null;
-  onSaveMessage: (role: "user" | "assistant", content: string) => Promise<void>;
+  onMessagesChanged: (conversationId: string) => Promise<void>;
   onCreateConversation: () => Promise<string>;
   messages: Message[];
 }
 
 export default function VoiceChat({
   currentConversationId,
-  onSaveMessage,
+  onMessagesChanged,
   onCreateConversation,
   messages,
 }: VoiceChatProps) {
@@ -22,13 +49,28 @@ export default function VoiceChat({
   const [transcribedText, setTranscribedText] = useState("");
   const [responseText, setResponseText] = useState("");
   const [isPlaying, setIsPlaying] = useState(false);
-  const [error, setError] = useState<string | null>(null);
+  const [error, setError] = useState<AppError | null>(null);
   const [showPermissionModal, setShowPermissionModal] = useState(false);
   const [recordingStartTime, setRecordingStartTime] = useState<number | null>(null);
+  const [liveCaption, setLiveCaption] = useState("");
+  const [handsFree, setHandsFree] = useState(false);
+  // Listening during replies only works when the reply can't reach the
+  // microphone, i.e. with headphones; through speakers it would barge in on itself
+  const [talkOver, setTalkOver] = useState(false);
+  const [stage, setStage] = useState<string | null>(null);
 
-  const mediaRecorderRef = useRef<MediaRecorder | null>(null);
-  const audioChunksRef = useRef<Blob[]>([]);
+  const streamSessionRef = useRef<string | null>(null);
+  const stopRecordingRef = useRef<() => void>(() => {});
+  const generationRef = useRef<string | null>(null);
   const audioRef = useRef<HTMLAudioElement | null>(null);
+  const speechRequestRef = useRef<string | null>(null);
+  const speechQueueRef = useRef<string[]>([]);
+  const speakingRef = useRef(false);
+  // Speech segments of the current reply whose playback has started
+  const playedSegmentsRef = useRef(0);
+  const bargeInRef = useRef<() => void>(() => {});
+  // Set when a hands-free turn completes, to listen again once its reply has played
+  const resumeListeningRef = useRef(false);
 
   // Get the latest messages for display
   const latestMessages = messages.slice(-2);
@@ -49,7 +91,98 @@ export default function VoiceChat({
     checkMicPermission();
   }, []);
 
-  const startRecording = async () => {
+  // Show live captions while the user is still talking
+  useEffect(() => {
+    const unlisten = listen<{ session_id: string; text: string }>("transcript-partial", (event) => {
+      if (event.payload.session_id === streamSessionRef.current) {
+        setLiveCaption(event.payload.text);
+      }
+    });
+
+    // In hands-free mode the backend tells us when the user stopped talking
+    const unlistenEnded = listen<{ session_id: string }>("utterance-ended", (event) => {
+      if (event.payload.session_id === streamSessionRef.current) {
+        stopRecordingRef.current();
+      }
+    });
+
+    // A microphone that fails mid-recording ends it; stopping surfaces the error
+    const unlistenFailed = listen<{ session_id: string }>("recording-failed", (event) => {
+      if (event.payload.session_id === streamSessionRef.current) {
+        stopRecordingRef.current();
+      }
+    });
+
+    // Talking over the reply interrupts it (hands-free with talk-over keeps listening while it plays)
+    const unlistenSpeechStarted = listen<{ session_id: string }>("speech-started", (event) => {
+      if (event.payload.session_id === streamSessionRef.current) {
+        bargeInRef.current();
+      }
+    });
+
+    // Show what the user said as soon as the final pass is done
+    const unlistenFinal = listen<{ session_id: string; text: string; language: string | null }>("transcript-final", (event) => {
+      if (event.payload.session_id === streamSessionRef.current) {
+        setLiveCaption("");
+        setTranscribedText(event.payload.text);
+      }
+    });
+
+    const unlistenStage = listen<{ turn_id: string; stage: string }>("turn-stage", (event) => {
+      if (event.payload.turn_id === generationRef.current) {
+        // "done", "cancelled" and "failed" end the turn
+        setStage(STAGE_LABELS[event.payload.stage] ? event.payload.stage : null);
+      }
+    });
+
+    // Render the answer as Ollama generates it
+    const unlistenTokens = listen<{ request_id: string; token: string }>("llm-token", (event) => {
+      if (event.payload.request_id === generationRef.current) {
+        setResponseText((text) => text + event.payload.token);
+      }
+    });
+
+    // Sentences are synthesized while the answer is still streaming; play them in order
+    const unlistenSpeech = listen<{ request_id: string; index: number; path: string }>("tts-segment", (event) => {
+      if (event.payload.request_id === speechRequestRef.current) {
+        speechQueueRef.current.push(event.payload.path);
+        playQueuedSpeech();
+      }
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+      unlistenEnded.then((fn) => fn());
+      unlistenFailed.then((fn) => fn());
+      unlistenSpeechStarted.then((fn) => fn());
+      unlistenTokens.then((fn) => fn());
+      unlistenFinal.then((fn) => fn());
+      unlistenStage.then((fn) => fn());
+      unlistenSpeech.then((fn) => fn());
+    };
+  }, []);
+
+  // Keep listening while the reply plays so the user can talk over it
+  useEffect(() => {
+    if (isPlaying && handsFree && talkOver && !isRecording) {
+      startRecording(true);
+    }
+  }, [isPlaying]);
+
+  // Otherwise wait for the reply to finish before listening for the next turn
+  useEffect(() => {
+    if (!resumeListeningRef.current || isPlaying || isProcessing) {
+      return;
+    }
+    resumeListeningRef.current = false;
+    if (handsFree && !isRecording) {
+      startRecording(true);
+    }
+  }, [isPlaying, isProcessing]);
+
+  // `keepResponse` leaves the reply on screen until the user actually speaks
+  const startRecording = async (keepResponse = false) => {
+    resumeListeningRef.current = false;
     try {
       setError(null);
       
@@ -57,129 +190,157 @@ export default function VoiceChat({
       try {
         const hasPermission = await invoke<boolean>("request_microphone_permission");
         if (!hasPermission) {
-          setError("Microphone access denied. Please enable microphone permissions in System Settings.");
+          setError(toAppError(MICROPHONE_DENIED, "microphone_unavailable"));
           return;
         }
       } catch (permissionError) {
         console.error("Permission request failed:", permissionError);
-        setError("Microphone access denied. Please enable microphone permissions in System Settings.");
+        setError(toAppError(permissionError));
         return;
       }
       
-      // Now try to get the media stream
-      const stream = await navigator.mediaDevices.getUserMedia({ 
-        audio: {
-          sampleRate: 48000,
-          channelCount: 1,
-          echoCancellation: true,
-          noiseSuppression: true
-        } 
-      });
+      // Record natively; the backend streams PCM straight into Whisper
+      const sessionId = await invoke<string>("start_recording", { handsFree });
       
-      // Try to use WAV format if supported, otherwise fall back to WebM
-      const options = { mimeType: 'audio/webm;codecs=opus' };
-      if (!MediaRecorder.isTypeSupported(options.mimeType)) {
-        console.log("WebM not supported, trying WAV");
-        options.mimeType = 'audio/wav';
+      streamSessionRef.current = sessionId;
+      setLiveCaption("");
+      if (!keepResponse) {
+        setTranscribedText("");
+        setResponseText("");
       }
-      
-      mediaRecorderRef.current = new MediaRecorder(stream, options);
-      audioChunksRef.current = [];
-
-      mediaRecorderRef.current.ondataavailable = (event) => {
-        audioChunksRef.current.push(event.data);
-      };
-
-      mediaRecorderRef.current.onstop = async () => {
-        const actualMimeType = mediaRecorderRef.current?.mimeType || "audio/webm";
-        const audioBlob = new Blob(audioChunksRef.current, { type: actualMimeType });
-        console.log("Audio blob created:", audioBlob.size, "bytes, type:", audioBlob.type);
-        await processAudio(audioBlob);
-        
-        // Stop all tracks to release microphone
-        stream.getTracks().forEach(track => track.stop());
-      };
-
-      mediaRecorderRef.current.start();
       setIsRecording(true);
       setRecordingStartTime(Date.now());
     } catch (error) {
       console.error("Failed to start recording:", error);
-      setError("Microphone access denied. Please enable microphone permissions in System Settings.");
+      setError(toAppError(error));
     }
   };
 
-  const stopRecording = () => {
-    if (mediaRecorderRef.current && isRecording) {
-      const recordingDuration = recordingStartTime ? Date.now() - recordingStartTime : 0;
-      
-      if (recordingDuration < 500) { // Less than 0.5 seconds
-        setError("Recording too short. Please hold the button for at least 0.5 seconds.");
-        setIsRecording(false);
-        setRecordingStartTime(null);
-        return;
-      }
-      
-      mediaRecorderRef.current.stop();
-      setIsRecording(false);
-      setRecordingStartTime(null);
+  const stopRecording = async () => {
+    if (!streamSessionRef.current || !isRecording) {
+      return;
+    }
+    
+    const recordingDuration = recordingStartTime ? Date.now() - recordingStartTime : 0;
+    setIsRecording(false);
+    setRecordingStartTime(null);
+    
+    if (recordingDuration < 500) { // Less than 0.5 seconds
+      streamSessionRef.current = null;
+      setError(toAppError("Recording too short. Please hold the button for at least 0.5 seconds.", "no_speech"));
+      setLiveCaption("");
+      await invoke("cancel_recording").catch(() => {});
+      return;
+    }
+    
+    await processRecording();
+  };
+
+  stopRecordingRef.current = stopRecording;
+
+  const cancelGeneration = async () => {
+    if (generationRef.current) {
+      await invoke("cancel_turn", { turnId: generationRef.current });
     }
   };
 
-  const processAudio = async (audioBlob: Blob) => {
-    setIsProcessing(true);
+  // Stops the reply the user is talking over and tells the backend how much of it was heard
+  const bargeIn = async () => {
+    const turnId = speechRequestRef.current;
+    if (!turnId || (!speakingRef.current && generationRef.current !== turnId)) {
+      return;
+    }
+
+    const playedSegments = playedSegmentsRef.current;
+    speechRequestRef.current = null;
+    generationRef.current = null;
+    speechQueueRef.current = [];
+    audioRef.current?.pause();
     setTranscribedText("");
     setResponseText("");
+    setStage(null);
+    setIsProcessing(false);
 
     try {
-      // Convert blob to array buffer
-      const arrayBuffer = await audioBlob.arrayBuffer();
-      const uint8Array = new Uint8Array(arrayBuffer);
-      
-      console.log("Sending audio data to backend:", uint8Array.length, "bytes");
-      
-      // Step 1: Process audio and transcribe
-      const transcription = await invoke<string>("process_audio_blob", {
-        audioData: Array.from(uint8Array),
-      });
-      
-      setTranscribedText(transcription);
-      
-      // Save user message
-      if (!currentConversationId) {
-        await onCreateConversation();
-      }
-      await onSaveMessage("user", transcription);
+      await invoke("interrupt_turn", { turnId, playedSegments });
+    } catch (error) {
+      console.error("Failed to interrupt turn:", error);
+    }
+  };
 
-      // Step 2: Send to Ollama
-      const response = await invoke<string>("send_prompt", {
-        prompt: transcription,
-      });
-      
-      setResponseText(response);
-      
-      // Save assistant message
-      await onSaveMessage("assistant", response);
+  bargeInRef.current = bargeIn;
 
-      // Step 3: Synthesize speech
-      const audioPath = await invoke<string>("synthesize_speech", {
-        text: response,
-      });
-      
-      // Play the synthesized audio
-      await playAudio(audioPath);
+  const processRecording = async () => {
+    setIsProcessing(true);
+
+    const sessionId = streamSessionRef.current;
+    const turnId = crypto.randomUUID();
+    // A barge-in hands the screen over to the next turn
+    const superseded = () => generationRef.current !== turnId;
+
+    try {
+      const conversationId = currentConversationId ?? await onCreateConversation();
+
+      // The backend runs the whole turn: transcription, reply, speech and saving.
+      // Tokens, speech segments and stage changes arrive as events keyed by the turn id.
+      generationRef.current = turnId;
+      speechRequestRef.current = turnId;
+      speechQueueRef.current = [];
+      playedSegmentsRef.current = 0;
+      setResponseText("");
+
+      const result = await invoke<TurnResult>("voice_turn", { turnId, conversationId });
+      console.log("Turn timings:", result.timings);
+
+      if (!superseded()) {
+        generationRef.current = null;
+        resumeListeningRef.current = !result.cancelled;
+        setLiveCaption("");
+        setTranscribedText(result.transcript);
+        setResponseText(result.response);
+      }
+      await onMessagesChanged(conversationId);
 
     } catch (error) {
       console.error("Failed to process audio:", error);
-      setError(error as string);
+      if (!superseded()) {
+        generationRef.current = null;
+        speechRequestRef.current = null;
+        setLiveCaption("");
+        setError(toAppError(error));
+      }
     } finally {
-      setIsProcessing(false);
+      // Hands-free may already be listening for the next turn
+      if (streamSessionRef.current === sessionId) {
+        streamSessionRef.current = null;
+      }
+      if (!superseded()) {
+        setStage(null);
+        setIsProcessing(false);
+      }
     }
   };
 
-  const playAudio = async (audioPath: string) => {
+  const playQueuedSpeech = async () => {
+    if (speakingRef.current) {
+      return;
+    }
+
+    speakingRef.current = true;
+    setIsPlaying(true);
+    while (speechQueueRef.current.length > 0) {
+      const audioPath = speechQueueRef.current.shift()!;
+      playedSegmentsRef.current += 1;
+      await playAudio(audioPath, speechRequestRef.current);
+    }
+    speakingRef.current = false;
+    setIsPlaying(false);
+  };
+
+  // Resolves once the clip has finished (or failed, or been interrupted).
+  // With `range`, only that part of the clip is played.
+  const playAudio = async (audioPath: string, requestId: string | null, range?: AudioRange) => {
     try {
-      setIsPlaying(true);
       console.log("Attempting to play audio from:", audioPath);
       
       // Try multiple approaches for audio playback
@@ -214,16 +375,26 @@ export default function VoiceChat({
       
       audioRef.current = audio;
       
-      audio.onended = () => {
-        setIsPlaying(false);
-      };
-      
-      audio.onerror = (e) => {
-        console.error("Audio playback error:", e);
-        setIsPlaying(false);
-        setError(`Failed to play audio response: ${e.type}`);
-      };
+      const finished = new Promise<void>((resolve) => {
+        audio.onended = () => resolve();
+        audio.onpause = () => resolve();
+        
+        audio.onerror = (e) => {
+          console.error("Audio playback error:", e);
+          setError(toAppError(`Failed to play audio response: ${typeof e === "string" ? e : e.type}`, "synthesis_failed"));
+          resolve();
+        };
+      });
       
+      if (range) {
+        audio.currentTime = range.startMs / 1000;
+        audio.ontimeupdate = () => {
+          if (audio.currentTime >= range.endMs / 1000) {
+            audio.pause();
+          }
+        };
+      }
+
       audio.onloadstart = () => {
         console.log("Audio loading started");
       };
@@ -232,24 +403,50 @@ export default function VoiceChat({
         console.log("Audio can start playing");
       };
       
+      // The user barged in while the clip was loading
+      if (speechRequestRef.current !== requestId) {
+        return;
+      }
+
       await audio.play();
       console.log("Audio playback started successfully");
+      await finished;
       
     } catch (error) {
       console.error("Failed to play audio:", error);
+      setError(toAppError(`Failed to play audio response: ${error}`, "synthesis_failed"));
+    }
+  };
+
+  // Plays a message's stored recording or spoken reply again, or part of it
+  const replayMessage = async (message: Message, range?: AudioRange) => {
+    if (!message.audio_file || isPlaying || isRecording) {
+      return;
+    }
+
+    try {
+      const audioPath = await invoke<string>("get_message_audio_path", { file: message.audio_file });
+      setIsPlaying(true);
+      await playAudio(audioPath, speechRequestRef.current, range);
+    } catch (error) {
+      setError(toAppError(error));
+    } finally {
       setIsPlaying(false);
-      setError(`Failed to play audio response: ${error}`);
     }
   };
 
   const handleMouseDown = () => {
-    if (!isProcessing) {
+    if (handsFree && isRecording) {
+      stopRecording();
+    } else if (!isRecording) {
+      // Pressing the button while the assistant answers interrupts it
+      bargeIn();
       startRecording();
     }
   };
 
   const handleMouseUp = () => {
-    if (isRecording) {
+    if (isRecording && !handsFree) {
       stopRecording();
     }
   };
@@ -271,7 +468,6 @@ export default function VoiceChat({
           onMouseUp={handleMouseUp}
           onMouseLeave={handleMouseUp}
           onContextMenu={handleContextMenu}
-          disabled={isProcessing}
           className={`
             w-24 h-24 rounded-full flex items-center justify-center text-white text-3xl
             transition-all duration-200 transform
@@ -281,8 +477,7 @@ export default function VoiceChat({
               ? "bg-yellow-500 animate-pulse"
               : "bg-blue-600 hover:bg-blue-700 hover:scale-105 shadow-lg"
             }
-            ${isProcessing ? "cursor-not-allowed" : "cursor-pointer"}
-            select-none
+            cursor-pointer select-none
           `}
         >
           {isProcessing ? (
@@ -303,19 +498,50 @@ export default function VoiceChat({
       {/* Instructions */}
       <div className="text-center text-gray-300">
         {isRecording ? (
-          <p className="text-lg font-medium">🔴 Recording... Release to send</p>
+          <p className="text-lg font-medium">
+            {handsFree ? "🔴 Listening... Pause to send" : "🔴 Recording... Release to send"}
+          </p>
         ) : isProcessing ? (
-          <p className="text-lg font-medium">⚙️ Processing...</p>
+          <p className="text-lg font-medium">{(stage && STAGE_LABELS[stage]) ?? "⚙️ Processing..."}</p>
         ) : isPlaying ? (
           <p className="text-lg font-medium">🔊 Playing response...</p>
         ) : (
-          <p className="text-lg">Hold the microphone button to record</p>
+          <p className="text-lg">
+            {handsFree ? "Click the microphone button and start talking" : "Hold the microphone button to record"}
+          </p>
+        )}
+        <label className="mt-2 inline-flex items-center space-x-2 text-sm text-gray-400">
+          <input
+            type="checkbox"
+            checked={handsFree}
+            disabled={isRecording || isProcessing}
+            onChange={(e) => setHandsFree(e.target.checked)}
+          />
+          <span>Hands-free</span>
+        </label>
+        {handsFree && (
+          <label className="mt-2 ml-4 inline-flex items-center space-x-2 text-sm text-gray-400">
+            <input
+              type="checkbox"
+              checked={talkOver}
+              disabled={isRecording || isProcessing}
+              onChange={(e) => setTalkOver(e.target.checked)}
+            />
+            <span>Talk over replies (headphones only)</span>
+          </label>
         )}
       </div>
 
       {/* Current Transcription and Response */}
-      {(transcribedText || responseText) && (
+      {(liveCaption || transcribedText || responseText) && (
         <div className="w-full space-y-4">
+          {liveCaption && !transcribedText && (
+            <div className="bg-blue-900/20 rounded-lg p-4 border border-blue-500/20">
+              <p className="text-sm text-blue-300 mb-1">Listening:</p>
+              <p className="text-gray-200 italic">{liveCaption}</p>
+            </div>
+          )}
+          
           {transcribedText && (
             <div className="bg-blue-900/30 rounded-lg p-4 border border-blue-500/30">
               <p className="text-sm text-blue-300 mb-1">You said:</p>
@@ -325,7 +551,17 @@ export default function VoiceChat({
           
           {responseText && (
             <div className="bg-purple-900/30 rounded-lg p-4 border border-purple-500/30">
-              <p className="text-sm text-purple-300 mb-1">Assistant:</p>
+              <div className="flex items-center justify-between mb-1">
+                <p className="text-sm text-purple-300">Assistant:</p>
+                {isProcessing && generationRef.current && (
+                  <button
+                    onClick={cancelGeneration}
+                    className="text-xs text-purple-300 hover:text-white underline"
+                  >
+                    Stop
+                  </button>
+                )}
+              </div>
               <p className="text-white">{responseText}</p>
             </div>
           )}
@@ -333,7 +569,7 @@ export default function VoiceChat({
       )}
 
       {/* Recent Messages */}
-      {latestMessages.length > 0 && !transcribedText && !responseText && (
+      {latestMessages.length > 0 && !liveCaption && !transcribedText && !responseText && (
         <div className="w-full space-y-4">
           <h3 className="text-lg font-medium text-gray-300 text-center">Recent Messages</h3>
           {latestMessages.map((message) => (
@@ -345,12 +581,50 @@ export default function VoiceChat({
                   : "bg-purple-900/20 border-purple-500/20"
               }`}
             >
-              <p className={`text-sm mb-1 ${
-                message.role === "user" ? "text-blue-300" : "text-purple-300"
-              }`}>
-                {message.role === "user" ? "You:" : "Assistant:"}
+              <div className="flex items-center justify-between mb-1">
+                <p className={`text-sm ${
+                  message.role === "user" ? "text-blue-300" : "text-purple-300"
+                }`}>
+                  {message.role === "user" ? "You:" : "Assistant:"}
+                  {message.language && message.language !== "en" && (
+                    <span className="ml-2 text-xs uppercase text-gray-400">{message.language}</span>
+                  )}
+                </p>
+                {message.audio_file && (
+                  <button
+                    onClick={() => replayMessage(message)}
+                    disabled={isPlaying || isRecording}
+                    className="text-xs text-gray-400 hover:text-white disabled:opacity-50"
+                    title={message.metadata?.audio_duration_ms
+                      ? `Replay (${(message.metadata.audio_duration_ms / 1000).toFixed(1)}s)`
+                      : "Replay"}
+                  >
+                    ▶
+                  </button>
+                )}
+              </div>
+              <p className="text-white text-sm">
+                {message.metadata?.segments?.length ? (
+                  // Click a word to hear that part of the recording
+                  message.metadata.segments.flatMap((segment) =>
+                    segment.words.map((word) => ({ word, unsureSegment: segment.low_confidence ?? false }))
+                  ).map(({ word, unsureSegment }, i) => (
+                    <span key={i}>
+                      {i > 0 && " "}
+                      <span
+                        onClick={() => replayMessage(message, { startMs: word.start_ms, endMs: word.end_ms })}
+                        title={`${Math.round(word.confidence * 100)}% confident${unsureSegment ? "; this part may be misheard" : ""}`}
+                        className={`${message.audio_file ? "cursor-pointer hover:underline" : ""} ${
+                          word.confidence < LOW_CONFIDENCE ? "text-yellow-300 underline decoration-dotted" : ""
+                        } ${unsureSegment ? "bg-yellow-500/20 rounded" : ""}`}
+                      >
+                        {word.text}
+                      </span>
+                    </span>
+                  ))
+                ) : message.interrupted ? message.spoken_content ?? message.content : message.content}
+                {message.interrupted && <span className="text-gray-400 italic"> (interrupted)</span>}
               </p>
-              <p className="text-white text-sm">{message.content}</p>
             </div>
           ))}
         </div>
@@ -361,8 +635,16 @@ export default function VoiceChat({
         <div className="w-full bg-red-900/30 border border-red-500/30 rounded-lg p-4">
           <div className="flex items-start justify-between">
             <div className="flex-1">
-              <p className="text-red-300 text-sm">{error}</p>
-              {error.includes("Microphone access denied") && (
+              <p className="text-red-300 text-sm">{error.message}</p>
+              {error.detail !== error.message && (
+                <p className="text-red-400/70 text-xs mt-1">{error.detail}</p>
+              )}
+              {RECOVERY_HINTS[error.code] ? (
+                <p className="text-gray-300 text-sm mt-2">{RECOVERY_HINTS[error.code]}</p>
+              ) : error.retryable && (
+                <p className="text-gray-300 text-sm mt-2">Please try again.</p>
+              )}
+              {error.code === "microphone_unavailable" && (
                 <button
                   onClick={() => setShowPermissionModal(true)}
                   className="mt-3 bg-blue-600 hover:bg-blue-700 text-white text-sm py-2 px-4 rounded-lg transition-colors inline-flex items-center space-x-2"
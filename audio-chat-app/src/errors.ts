@@ -0,0 +1,31 @@
+// Mirrors the serialized `AppError` returned by every backend command
+export interface AppError {
+  code: string;
+  message: string;
+  detail: string;
+  retryable: boolean;
+  causes: string[];
+}
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === "object" && error !== null && "code" in error && "message" in error;
+}
+
+// Normalizes anything thrown by `invoke` or the browser into an AppError
+export function toAppError(error: unknown, code = "internal"): AppError {
+  if (isAppError(error)) {
+    return error;
+  }
+
+  const message = error instanceof Error ? error.message : String(error);
+  return { code, message, detail: message, retryable: false, causes: [] };
+}
+
+// Recovery hints for failures the user can fix themselves
+export const RECOVERY_HINTS: Record<string, string> = {
+  ollama_unavailable: "Start Ollama in a terminal with `ollama run gemma3n:latest`, then try again.",
+  ollama_failed: "Make sure the model is pulled with `ollama pull gemma3n:latest`.",
+  model_missing: "Download ggml-base.en.bin into src-tauri/models and restart the app.",
+  unsupported_audio: "Enable \"ffmpeg_fallback\" in settings.json to decode this format with ffmpeg.",
+  synthesis_failed: "Check that the configured text-to-speech engine is installed.",
+};